@@ -1,28 +1,56 @@
-#![feature(test)]
-extern crate test;
+#[macro_use]
+extern crate criterion;
 extern crate sheep;
 
-use test::Bencher;
-use sheep::{Packer, SpriteData, MaxrectsPacker, MaxrectsOptions};
+use criterion::{black_box, Criterion};
+use sheep::{MaxrectsOptions, MaxrectsPacker, Packer, SpriteData};
 
-#[bench]
-fn bench_pack(b: &mut Bencher) {
-    let mut sprites = (0..1000)
-        .map(|i| SpriteData::new(i, (100, 100)))
-        .collect::<Vec<SpriteData>>();
+// Two sizes only, so most sprites share their score with many others
+fn uniform_sprites() -> Vec<SpriteData> {
+    (0..2000)
+        .map(|i| {
+            let size = if i < 1000 { 100 } else { 80 };
+            SpriteData::new(i, (size, size))
+        })
+        .collect()
+}
 
-    let smaller_sprites = (0..1000)
-        .map(|i| SpriteData::new(i, (80, 80)))
-        .collect::<Vec<SpriteData>>();
+// Sizes between 8 and 71 pixels from a fixed seed, like a typical set of
+// trimmed sprites
+fn varied_sprites(count: usize) -> Vec<SpriteData> {
+    let mut seed = 0x2545_f491_u32;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        8 + seed % 64
+    };
 
-    sprites.extend(smaller_sprites.into_iter());
+    (0..count)
+        .map(|i| SpriteData::new(i, (next(), next())))
+        .collect()
+}
 
-    let options = MaxrectsOptions::default()
-        .preferred_width(1000 * 100)
-        .preferred_height(1000 * 100);
+fn bench_pack(c: &mut Criterion) {
+    let options = MaxrectsOptions::default();
 
-    b.iter(|| {
-        let result = MaxrectsPacker::pack(&sprites, options);
-        let _ = test::black_box(&result);
+    let mut group = c.benchmark_group("maxrects");
+    group.sample_size(10);
+
+    let uniform = uniform_sprites();
+    group.bench_function("uniform_2000", |b| {
+        b.iter(|| MaxrectsPacker::pack(black_box(&uniform), options))
     });
-}
\ No newline at end of file
+
+    for &count in &[1000, 5000] {
+        let varied = varied_sprites(count);
+        group.bench_function(format!("varied_{}", count), |b| {
+            b.iter(|| MaxrectsPacker::pack(black_box(&varied), options))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_pack);
+criterion_main!(benches);
@@ -15,7 +15,7 @@ fn main() {
     let dimensions = img.dimensions();
     let bytes = img
         .pixels()
-        .flat_map(|it| it.data.iter().map(|it| *it))
+        .flat_map(|it| it.data.iter().cloned())
         .collect::<Vec<u8>>();
 
     // We'll just repeat the same sprite 16 times and pack it into a texture.
@@ -29,9 +29,9 @@ fn main() {
     // have 4 bytes per pixel.
     let results = sheep::pack::<SimplePacker>(sprites, 4, Default::default());
 
-    // SimplePacker always returns a single result. Other packers can return
-    // multiple sheets; should they, for example, choose to enforce a maximum
-    // texture size per sheet.
+    // Without a maximum size, SimplePacker always returns a single result.
+    // Packers can return multiple sheets; should they, for example, enforce
+    // a maximum texture size per sheet.
     let sprite_sheet = results
         .into_iter()
         .next()
@@ -0,0 +1,382 @@
+use pack::maxrects::free_rects;
+use {InputSprite, SpriteAnchor};
+
+// Digits of a 3x5 pixel font, row by row from the top, with the highest
+// bit being the top left pixel
+const DIGITS: [u16; 10] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+];
+
+// Letters in the same font, also used for lower case letters
+const LETTERS: [u16; 26] = [
+    0b010_101_111_101_101,
+    0b110_101_110_101_110,
+    0b011_100_100_100_011,
+    0b110_101_101_101_110,
+    0b111_100_110_100_111,
+    0b111_100_110_100_100,
+    0b011_100_101_101_011,
+    0b101_101_111_101_101,
+    0b111_010_010_010_111,
+    0b001_001_001_101_010,
+    0b101_101_110_101_101,
+    0b100_100_100_100_111,
+    0b101_111_111_101_101,
+    0b110_101_101_101_101,
+    0b010_101_101_101_010,
+    0b110_101_110_100_100,
+    0b010_101_101_110_011,
+    0b110_101_110_101_101,
+    0b011_100_010_001_110,
+    0b111_010_010_010_010,
+    0b101_101_101_101_111,
+    0b101_101_101_101_010,
+    0b101_101_111_111_101,
+    0b101_101_010_101_101,
+    0b101_101_010_010_010,
+    0b111_001_010_100_111,
+];
+
+// Drawn for characters the font doesn't have
+const UNKNOWN: u16 = 0b111_001_010_000_010;
+
+const FREE_COLOR: [u8; 3] = [255, 0, 255];
+
+/// Settings for `debug_overlay`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugOptions {
+    scale: u32,
+    padding: u32,
+    free_rects: bool,
+    labels: bool,
+    names: Vec<String>,
+}
+
+impl Default for DebugOptions {
+    fn default() -> Self {
+        DebugOptions {
+            scale: 1,
+            padding: 0,
+            free_rects: true,
+            labels: true,
+            names: Vec::new(),
+        }
+    }
+}
+
+impl DebugOptions {
+    /// Scales the sheet up by this factor, so outlines and labels cover
+    /// less of the sprites.
+    pub fn scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    /// The padding the sheet was packed with, which isn't shown as free
+    /// space.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Highlights the empty rectangles that further sprites could still be
+    /// placed into, as the maxrects packer sees them.
+    pub fn free_rects(mut self, free_rects: bool) -> Self {
+        self.free_rects = free_rects;
+        self
+    }
+
+    /// Writes the name of every sprite into its top left corner, cut off
+    /// where the sprite ends, or its id if it fits and there is no name.
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// The sprite names used as labels, indexed by sprite id.
+    pub fn names(mut self, names: Vec<String>) -> Self {
+        self.names = names;
+        self
+    }
+}
+
+/// Draws the outline of every anchor onto a copy of the sheet, each in its
+/// own color, to check where a packer placed the sprites. Transparent areas
+/// are shown as a checkerboard. The result is always rgba8, regardless of
+/// the stride of the sheet.
+pub fn debug_overlay(
+    sheet: &InputSprite,
+    stride: usize,
+    alpha_channel_index: usize,
+    anchors: &[SpriteAnchor],
+    options: DebugOptions,
+) -> InputSprite {
+    let scale = options.scale;
+    let (width, height) = sheet.dimensions;
+    let mut image = Image::new(width * scale, height * scale);
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let index = ((y / scale * width + x / scale) as usize) * stride;
+            let pixel = &sheet.bytes[index..index + stride];
+            let alpha = u32::from(pixel[alpha_channel_index]);
+
+            let background = if (x / 8 + y / 8) % 2 == 0 { 96 } else { 64 };
+            let mut color = [background; 3];
+            for (channel, value) in color.iter_mut().enumerate() {
+                let source = if channel < stride && channel != alpha_channel_index {
+                    u32::from(pixel[channel])
+                } else {
+                    0
+                };
+                *value = ((source * alpha + u32::from(*value) * (255 - alpha)) / 255) as u8;
+            }
+            image.set(x, y, color);
+        }
+    }
+
+    if options.free_rects {
+        for (position, dimensions) in free_rects(sheet.dimensions, anchors, options.padding) {
+            let rect = scaled(position, dimensions, scale);
+            image.blend(rect, FREE_COLOR);
+            image.outline(rect, FREE_COLOR);
+        }
+    }
+
+    for anchor in anchors {
+        if anchor.dimensions.0 == 0 || anchor.dimensions.1 == 0 {
+            continue;
+        }
+
+        let color = id_color(anchor.id);
+        let rect = scaled(anchor.position, anchor.dimensions, scale);
+        image.outline(rect, color);
+
+        if options.labels {
+            match options.names.get(anchor.id) {
+                Some(name) => image.label(rect, name, true, color),
+                None => image.label(rect, &anchor.id.to_string(), false, color),
+            }
+        }
+    }
+
+    InputSprite {
+        dimensions: (image.width, image.height),
+        bytes: image.bytes,
+    }
+}
+
+// Spreads the hues of consecutive ids out by the golden angle, so sprites
+// next to each other get clearly different colors
+fn id_color(id: usize) -> [u8; 3] {
+    let hue = (id as f32 * 0.618_034).fract() * 6.0;
+    let fraction = hue.fract();
+    let (high, low) = (255.0, 64.0);
+    let rising = low + (high - low) * fraction;
+    let falling = high - (high - low) * fraction;
+
+    let color = match hue as u32 {
+        0 => [high, rising, low],
+        1 => [falling, high, low],
+        2 => [low, high, rising],
+        3 => [low, falling, high],
+        4 => [rising, low, high],
+        _ => [high, low, falling],
+    };
+    [color[0] as u8, color[1] as u8, color[2] as u8]
+}
+
+fn scaled(position: (u32, u32), dimensions: (u32, u32), scale: u32) -> [u32; 4] {
+    [
+        position.0 * scale,
+        position.1 * scale,
+        dimensions.0 * scale,
+        dimensions.1 * scale,
+    ]
+}
+
+// An rgba8 image to draw into. Rects are given as (x, y, width, height).
+struct Image {
+    width: u32,
+    height: u32,
+    bytes: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            bytes: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    fn set(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let index = ((y * self.width + x) * 4) as usize;
+        self.bytes[index..index + 3].copy_from_slice(&color);
+        self.bytes[index + 3] = 255;
+    }
+
+    // Mixes a quarter of the color into the rect
+    fn blend(&mut self, [x, y, width, height]: [u32; 4], color: [u8; 3]) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                let index = ((py * self.width + px) * 4) as usize;
+                for (channel, value) in color.iter().enumerate() {
+                    let old = u32::from(self.bytes[index + channel]);
+                    self.bytes[index + channel] = ((old * 3 + u32::from(*value)) / 4) as u8;
+                }
+            }
+        }
+    }
+
+    fn outline(&mut self, [x, y, width, height]: [u32; 4], color: [u8; 3]) {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        for px in x..x + width {
+            self.set(px, y, color);
+            self.set(px, y + height - 1, color);
+        }
+        for py in y..y + height {
+            self.set(x, py, color);
+            self.set(x + width - 1, py, color);
+        }
+    }
+
+    // Draws the text on a black background inside the top left corner of
+    // the rect. Text that doesn't fit is cut off if `truncate` is set, and
+    // left out otherwise.
+    fn label(
+        &mut self,
+        [x, y, width, height]: [u32; 4],
+        text: &str,
+        truncate: bool,
+        color: [u8; 3],
+    ) {
+        let mut glyphs = text.chars().map(glyph).collect::<Vec<u16>>();
+        let fitting = (width.saturating_sub(3) / 4) as usize;
+        if glyphs.len() > fitting {
+            if !truncate {
+                return;
+            }
+            glyphs.truncate(fitting);
+        }
+        if glyphs.is_empty() || 9 > height {
+            return;
+        }
+
+        let label_width = glyphs.len() as u32 * 4 + 1;
+        for py in y + 1..y + 8 {
+            for px in x + 1..x + 1 + label_width {
+                self.set(px, py, [0, 0, 0]);
+            }
+        }
+
+        for (i, glyph) in glyphs.iter().enumerate() {
+            let left = x + 2 + i as u32 * 4;
+            for row in 0..5 {
+                for column in 0..3 {
+                    if glyph & (1 << (14 - row * 3 - column)) != 0 {
+                        self.set(left + column, y + 2 + row, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        c @ '0'..='9' => DIGITS[c as usize - '0' as usize],
+        c @ 'A'..='Z' => LETTERS[c as usize - 'A' as usize],
+        '_' => 0b000_000_000_000_111,
+        '-' => 0b000_000_111_000_000,
+        '/' => 0b001_001_010_100_100,
+        '.' => 0b000_000_000_000_010,
+        ' ' => 0,
+        _ => UNKNOWN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(image: &InputSprite, x: u32, y: u32) -> &[u8] {
+        let index = ((y * image.dimensions.0 + x) * 4) as usize;
+        &image.bytes[index..index + 4]
+    }
+
+    #[test]
+    fn overlay_outlines() {
+        let sheet = InputSprite {
+            bytes: vec![255; 20 * 10 * 4],
+            dimensions: (20, 10),
+        };
+        let anchors = vec![SpriteAnchor::new(7, (0, 0), (10, 10))];
+
+        let options = DebugOptions::default().scale(2);
+        let overlay = debug_overlay(&sheet, 4, 3, &anchors, options);
+        assert_eq!(overlay.dimensions, (40, 20));
+
+        let color = id_color(7);
+        assert_eq!(&pixel(&overlay, 0, 0)[..3], &color);
+        assert_eq!(&pixel(&overlay, 19, 19)[..3], &color);
+        assert_eq!(pixel(&overlay, 10, 10), &[255, 255, 255, 255]);
+
+        // The label is drawn in the corner, and the free right half is
+        // outlined and tinted
+        assert_eq!(pixel(&overlay, 1, 1), &[0, 0, 0, 255]);
+        assert_eq!(&pixel(&overlay, 20, 0)[..3], &FREE_COLOR);
+        assert_eq!(pixel(&overlay, 30, 10), &[255, 191, 255, 255]);
+    }
+
+    #[test]
+    fn overlay_names() {
+        let sheet = InputSprite {
+            bytes: vec![255; 40 * 10 * 4],
+            dimensions: (40, 10),
+        };
+        let anchors = vec![
+            SpriteAnchor::new(0, (0, 0), (12, 10)),
+            SpriteAnchor::new(1, (12, 0), (28, 10)),
+        ];
+
+        // Only the first two letters of the name fit, and the second sprite
+        // falls back to its id
+        let options = DebugOptions::default()
+            .free_rects(false)
+            .names(vec![String::from("abc")]);
+        let overlay = debug_overlay(&sheet, 4, 3, &anchors, options);
+
+        let color = id_color(0);
+        assert_eq!(&pixel(&overlay, 3, 2)[..3], &color);
+        assert_eq!(&pixel(&overlay, 6, 2)[..3], &color);
+        assert_eq!(pixel(&overlay, 2, 2), &[0, 0, 0, 255]);
+        assert_eq!(pixel(&overlay, 10, 5), &[255, 255, 255, 255]);
+        assert_eq!(&pixel(&overlay, 15, 2)[..3], &id_color(1));
+    }
+
+    #[test]
+    fn distinct_colors() {
+        let colors = (0..8).map(id_color).collect::<Vec<[u8; 3]>>();
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[i + 1..].contains(color));
+        }
+    }
+}
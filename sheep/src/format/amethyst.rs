@@ -3,7 +3,7 @@ use SpriteAnchor;
 
 pub struct AmethystFormat;
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct SpritePosition {
     pub x: f32,
     pub y: f32,
@@ -12,13 +12,35 @@ pub struct SpritePosition {
     pub offsets: Option<[f32; 2]>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct SerializedSpriteSheet {
     pub texture_width: f32,
     pub texture_height: f32,
     pub sprites: Vec<SpritePosition>,
 }
 
+impl SerializedSpriteSheet {
+    /// Turns the sprite positions back into anchors, using the position of
+    /// each sprite in `sprites` as its id.
+    pub fn anchors(&self) -> Vec<SpriteAnchor> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .map(|(id, sprite)| anchor(id, [sprite.x, sprite.y, sprite.width, sprite.height]))
+            .collect()
+    }
+}
+
+// Turns a deserialized position, given as x, y, width and height, back
+// into an anchor. Shared with the named format.
+pub(crate) fn anchor(id: usize, [x, y, width, height]: [f32; 4]) -> SpriteAnchor {
+    SpriteAnchor {
+        id,
+        position: (x as u32, y as u32),
+        dimensions: (width as u32, height as u32),
+    }
+}
+
 impl Format for AmethystFormat {
     type Data = SerializedSpriteSheet;
     type Options = ();
@@ -0,0 +1,176 @@
+use super::{escape_xml, trim_info, Format};
+use std::fmt::Write;
+use {SpriteAnchor, TrimInfo};
+
+/// Writes a Cocos2d sprite frame `.plist` (format version 2).
+pub struct Cocos2dFormat;
+
+/// `texture` is the filename of the sheet image, relative to the plist,
+/// and `names` are the sprite names, indexed by sprite id.
+#[derive(Clone, Debug)]
+pub struct Cocos2dOptions {
+    texture: String,
+    names: Vec<String>,
+    trims: Vec<TrimInfo>,
+}
+
+impl Cocos2dOptions {
+    pub fn new(texture: String, names: Vec<String>) -> Self {
+        Cocos2dOptions {
+            texture,
+            names,
+            trims: Vec::new(),
+        }
+    }
+
+    /// How the sprites were trimmed, indexed by sprite id, so the frames
+    /// describe the untrimmed images. See `sheep::trim_info`.
+    pub fn trims(mut self, trims: Vec<TrimInfo>) -> Self {
+        self.trims = trims;
+        self
+    }
+}
+
+impl Format for Cocos2dFormat {
+    type Data = String;
+    type Options = Cocos2dOptions;
+
+    fn encode(
+        dimensions: (u32, u32),
+        sprites: &[SpriteAnchor],
+        options: Self::Options,
+    ) -> Self::Data {
+        let Cocos2dOptions {
+            texture,
+            names,
+            trims,
+        } = options;
+        let texture = escape_xml(&texture);
+        let mut out = String::new();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            out,
+            r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#
+        )
+        .unwrap();
+        writeln!(out, r#"<plist version="1.0">"#).unwrap();
+        writeln!(out, "    <dict>").unwrap();
+        writeln!(out, "        <key>frames</key>").unwrap();
+        writeln!(out, "        <dict>").unwrap();
+
+        for anchor in sprites {
+            let (x, y) = anchor.position;
+            let (width, height) = anchor.dimensions;
+            let trim = trim_info(&trims, anchor);
+            let (left, top) = trim.offset;
+            let (original_width, original_height) = trim.original;
+
+            // The offset goes from the center of the untrimmed image to the
+            // center of the trimmed one, with y pointing up
+            let offset_x =
+                (2 * i64::from(left) + i64::from(width) - i64::from(original_width)) as f32 / 2.0;
+            let offset_y =
+                (i64::from(original_height) - 2 * i64::from(top) - i64::from(height)) as f32 / 2.0;
+
+            writeln!(
+                out,
+                "            <key>{}</key>",
+                escape_xml(&names[anchor.id])
+            )
+            .unwrap();
+            writeln!(out, "            <dict>").unwrap();
+            writeln!(out, "                <key>frame</key>").unwrap();
+            writeln!(
+                out,
+                "                <string>{{{{{},{}}},{{{},{}}}}}</string>",
+                x, y, width, height
+            )
+            .unwrap();
+            writeln!(out, "                <key>offset</key>").unwrap();
+            writeln!(
+                out,
+                "                <string>{{{},{}}}</string>",
+                offset_x, offset_y
+            )
+            .unwrap();
+            writeln!(out, "                <key>rotated</key>").unwrap();
+            writeln!(out, "                <false/>").unwrap();
+            writeln!(out, "                <key>sourceColorRect</key>").unwrap();
+            writeln!(
+                out,
+                "                <string>{{{{{},{}}},{{{},{}}}}}</string>",
+                left, top, width, height
+            )
+            .unwrap();
+            writeln!(out, "                <key>sourceSize</key>").unwrap();
+            writeln!(
+                out,
+                "                <string>{{{},{}}}</string>",
+                original_width, original_height
+            )
+            .unwrap();
+            writeln!(out, "            </dict>").unwrap();
+        }
+
+        writeln!(out, "        </dict>").unwrap();
+        writeln!(out, "        <key>metadata</key>").unwrap();
+        writeln!(out, "        <dict>").unwrap();
+        writeln!(out, "            <key>format</key>").unwrap();
+        writeln!(out, "            <integer>2</integer>").unwrap();
+        writeln!(out, "            <key>realTextureFileName</key>").unwrap();
+        writeln!(out, "            <string>{}</string>", texture).unwrap();
+        writeln!(out, "            <key>size</key>").unwrap();
+        writeln!(
+            out,
+            "            <string>{{{},{}}}</string>",
+            dimensions.0, dimensions.1
+        )
+        .unwrap();
+        writeln!(out, "            <key>textureFileName</key>").unwrap();
+        writeln!(out, "            <string>{}</string>", texture).unwrap();
+        writeln!(out, "        </dict>").unwrap();
+        writeln!(out, "    </dict>").unwrap();
+        writeln!(out, "</plist>").unwrap();
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_frames() {
+        let sprites = vec![SpriteAnchor::new(0, (4, 8), (10, 20))];
+        let options = Cocos2dOptions::new(String::from("out.png"), vec![String::from("a")]);
+
+        let plist = Cocos2dFormat::encode((16, 32), &sprites, options);
+
+        assert!(plist.contains("<key>a</key>"));
+        assert!(plist.contains("<string>{{4,8},{10,20}}</string>"));
+        assert!(plist.contains("<string>{0,0}</string>"));
+        assert!(plist.contains("<string>{{0,0},{10,20}}</string>"));
+        assert!(plist.contains("<string>{10,20}</string>"));
+        assert!(plist.contains("<string>{16,32}</string>"));
+    }
+
+    #[test]
+    fn encode_trimmed_frames() {
+        let sprites = vec![SpriteAnchor::new(0, (4, 8), (10, 20))];
+        let trim = TrimInfo {
+            offset: (3, 2),
+            original: (15, 30),
+            trimmed: (10, 20),
+        };
+        let options =
+            Cocos2dOptions::new(String::from("out.png"), vec![String::from("a")]).trims(vec![trim]);
+
+        let plist = Cocos2dFormat::encode((16, 32), &sprites, options);
+
+        assert!(plist.contains("<string>{0.5,3}</string>"));
+        assert!(plist.contains("<string>{{3,2},{10,20}}</string>"));
+        assert!(plist.contains("<string>{15,30}</string>"));
+    }
+}
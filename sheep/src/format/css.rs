@@ -0,0 +1,246 @@
+use super::{escape_xml, Format};
+use std::collections::HashSet;
+use std::fmt::Write;
+use SpriteAnchor;
+
+/// Writes a stylesheet with one class per sprite, and optionally an html
+/// page that previews every sprite along with its name.
+pub struct CssFormat;
+
+/// `image` is the url of the sheet image, relative to the stylesheet, and
+/// `names` are the sprite names, indexed by sprite id.
+#[derive(Clone, Debug)]
+pub struct CssOptions {
+    image: String,
+    names: Vec<String>,
+    prefix: String,
+    html: bool,
+}
+
+impl CssOptions {
+    pub fn new(image: String, names: Vec<String>) -> Self {
+        CssOptions {
+            image,
+            names,
+            prefix: String::from("sprite"),
+            html: false,
+        }
+    }
+
+    /// The class shared by all sprites. Every sprite also gets its own
+    /// class, named `<prefix>-<name>`, with a number appended if two names
+    /// end up with the same class.
+    pub fn prefix(mut self, prefix: String) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    pub fn html(mut self, html: bool) -> Self {
+        self.html = html;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CssSpriteSheet {
+    pub css: String,
+    pub html: Option<String>,
+}
+
+impl Format for CssFormat {
+    type Data = CssSpriteSheet;
+    type Options = CssOptions;
+
+    fn encode(
+        _dimensions: (u32, u32),
+        sprites: &[SpriteAnchor],
+        options: Self::Options,
+    ) -> Self::Data {
+        let prefix = class_name(&options.prefix);
+        let classes = sprite_classes(sprites, &options.names);
+        let mut css = String::new();
+
+        writeln!(css, ".{} {{", prefix).unwrap();
+        writeln!(
+            css,
+            "    background-image: url(\"{}\");",
+            options.image.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+        .unwrap();
+        writeln!(css, "    background-repeat: no-repeat;").unwrap();
+        writeln!(css, "    display: inline-block;").unwrap();
+        writeln!(css, "}}").unwrap();
+
+        for (anchor, class) in sprites.iter().zip(&classes) {
+            writeln!(css).unwrap();
+            writeln!(css, ".{}-{} {{", prefix, class).unwrap();
+            writeln!(
+                css,
+                "    background-position: {} {};",
+                offset(anchor.position.0),
+                offset(anchor.position.1)
+            )
+            .unwrap();
+            writeln!(css, "    width: {}px;", anchor.dimensions.0).unwrap();
+            writeln!(css, "    height: {}px;", anchor.dimensions.1).unwrap();
+            writeln!(css, "}}").unwrap();
+        }
+
+        let html = if options.html {
+            let mut html = String::new();
+
+            // The stylesheet is embedded so the page works on its own
+            writeln!(html, "<!DOCTYPE html>").unwrap();
+            writeln!(html, "<html>").unwrap();
+            writeln!(html, "<head>").unwrap();
+            writeln!(html, "<meta charset=\"utf-8\">").unwrap();
+            writeln!(html, "<title>{}</title>", escape_xml(&options.image)).unwrap();
+            writeln!(html, "<style>").unwrap();
+            write!(html, "{}", css).unwrap();
+            writeln!(html, "figure {{ display: inline-block; margin: 8px; }}").unwrap();
+            writeln!(html, "</style>").unwrap();
+            writeln!(html, "</head>").unwrap();
+            writeln!(html, "<body>").unwrap();
+
+            for (anchor, class) in sprites.iter().zip(&classes) {
+                let name = &options.names[anchor.id];
+
+                writeln!(html, "<figure>").unwrap();
+                writeln!(
+                    html,
+                    "<div class=\"{} {}-{}\"></div>",
+                    prefix, prefix, class
+                )
+                .unwrap();
+                writeln!(html, "<figcaption>{}</figcaption>", escape_xml(name)).unwrap();
+                writeln!(html, "</figure>").unwrap();
+            }
+
+            writeln!(html, "</body>").unwrap();
+            writeln!(html, "</html>").unwrap();
+
+            Some(html)
+        } else {
+            None
+        };
+
+        CssSpriteSheet { css, html }
+    }
+}
+
+fn offset(position: u32) -> String {
+    if position == 0 {
+        String::from("0")
+    } else {
+        format!("-{}px", position)
+    }
+}
+
+// The class of every sprite, in the order of the anchors. Names that end
+// up with the same class, like `player/run` and `player-run`, are told
+// apart by a numbered suffix.
+fn sprite_classes(sprites: &[SpriteAnchor], names: &[String]) -> Vec<String> {
+    let mut taken = HashSet::new();
+    sprites
+        .iter()
+        .map(|anchor| {
+            let base = class_name(&names[anchor.id]);
+            let mut class = base.clone();
+            let mut suffix = 1;
+            while taken.contains(&class) {
+                class = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+
+            taken.insert(class.clone());
+            class
+        })
+        .collect()
+}
+
+// Anything that isn't safe to use in a class selector becomes a dash,
+// so `player/run_01` turns into `player-run_01`
+fn class_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_classes() {
+        let sprites = vec![
+            SpriteAnchor::new(0, (0, 0), (10, 20)),
+            SpriteAnchor::new(1, (10, 5), (5, 5)),
+        ];
+        let options = CssOptions::new(
+            String::from("out.png"),
+            vec![String::from("a"), String::from("player/run 01")],
+        );
+
+        let sheet = CssFormat::encode((15, 20), &sprites, options);
+        let expected = r#".sprite {
+    background-image: url("out.png");
+    background-repeat: no-repeat;
+    display: inline-block;
+}
+
+.sprite-a {
+    background-position: 0 0;
+    width: 10px;
+    height: 20px;
+}
+
+.sprite-player-run-01 {
+    background-position: -10px -5px;
+    width: 5px;
+    height: 5px;
+}
+"#;
+
+        assert_eq!(sheet.css, expected);
+        assert_eq!(sheet.html, None);
+    }
+
+    #[test]
+    fn encode_html() {
+        let sprites = vec![SpriteAnchor::new(0, (0, 0), (10, 20))];
+        let options = CssOptions::new(String::from("out.png"), vec![String::from("<a>")])
+            .prefix(String::from("icon"))
+            .html(true);
+
+        let html = CssFormat::encode((10, 20), &sprites, options)
+            .html
+            .expect("html should have been generated");
+
+        assert!(html.contains("<div class=\"icon icon--a-\"></div>"));
+        assert!(html.contains("<figcaption>&lt;a&gt;</figcaption>"));
+    }
+
+    #[test]
+    fn unique_classes() {
+        let sprites = (0..4)
+            .map(|id| SpriteAnchor::new(id, (0, 0), (1, 1)))
+            .collect::<Vec<SpriteAnchor>>();
+        let names = vec![
+            String::from("run/01"),
+            String::from("run-01"),
+            String::from("run 01"),
+            String::from("run-01-1"),
+        ];
+
+        assert_eq!(
+            sprite_classes(&sprites, &names),
+            vec!["run-01", "run-01-1", "run-01-2", "run-01-1-1"]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use super::MultiFormat;
+use SpriteAnchor;
+
+/// Ties together all sheets returned by a single call to `pack`, mapping
+/// every sprite to the page it was placed on and its position there.
+pub struct IndexFormat;
+
+/// `pages` are the image filenames of the sheets, relative to the index.
+#[derive(Clone, Debug)]
+pub struct IndexOptions {
+    pages: Vec<String>,
+    names: Option<Vec<String>>,
+}
+
+impl IndexOptions {
+    pub fn new(pages: Vec<String>) -> Self {
+        IndexOptions { pages, names: None }
+    }
+
+    /// Include the sprite names, indexed by sprite id, in the output.
+    pub fn names(mut self, names: Vec<String>) -> Self {
+        self.names = Some(names);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct IndexPage {
+    pub image: String,
+    pub texture_width: f32,
+    pub texture_height: f32,
+}
+
+/// `page` is the index of the page in `SerializedAtlasIndex::pages`, the
+/// remaining fields mimic the `SpritePosition` struct.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct IndexedSpritePosition {
+    pub id: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub page: usize,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// The sprites are sorted by their id, so they are in the same order as the
+/// input sprites.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SerializedAtlasIndex {
+    pub pages: Vec<IndexPage>,
+    pub sprites: Vec<IndexedSpritePosition>,
+}
+
+impl SerializedAtlasIndex {
+    pub fn find(&self, name: &str) -> Option<&IndexedSpritePosition> {
+        self.sprites
+            .iter()
+            .find(|sprite| sprite.name.as_deref() == Some(name))
+    }
+}
+
+impl MultiFormat for IndexFormat {
+    type Data = SerializedAtlasIndex;
+    type Options = IndexOptions;
+
+    fn encode(sheets: &[((u32, u32), &[SpriteAnchor])], options: Self::Options) -> Self::Data {
+        let IndexOptions { pages, names } = options;
+
+        let pages = sheets
+            .iter()
+            .zip(pages)
+            .map(|((dimensions, _), image)| IndexPage {
+                image,
+                texture_width: dimensions.0 as f32,
+                texture_height: dimensions.1 as f32,
+            })
+            .collect::<Vec<IndexPage>>();
+
+        let mut sprites = sheets
+            .iter()
+            .enumerate()
+            .flat_map(|(page, (_, anchors))| anchors.iter().map(move |anchor| (page, anchor)))
+            .map(|(page, anchor)| IndexedSpritePosition {
+                id: anchor.id,
+                name: names.as_ref().map(|names| names[anchor.id].clone()),
+                page,
+                x: anchor.position.0 as f32,
+                y: anchor.position.1 as f32,
+                width: anchor.dimensions.0 as f32,
+                height: anchor.dimensions.1 as f32,
+            })
+            .collect::<Vec<IndexedSpritePosition>>();
+
+        sprites.sort_by_key(|sprite| sprite.id);
+
+        SerializedAtlasIndex { pages, sprites }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_pages() {
+        let first = vec![
+            SpriteAnchor::new(2, (0, 0), (10, 10)),
+            SpriteAnchor::new(0, (10, 0), (10, 10)),
+        ];
+        let second = vec![SpriteAnchor::new(1, (0, 0), (30, 30))];
+        let sheets = vec![((20, 10), first.as_slice()), ((30, 30), second.as_slice())];
+
+        let pages = vec![String::from("out-00.png"), String::from("out-01.png")];
+        let names = vec![String::from("a"), String::from("b"), String::from("c")];
+        let options = IndexOptions::new(pages).names(names);
+        let index = IndexFormat::encode(&sheets, options);
+
+        assert_eq!(index.pages.len(), 2);
+        assert_eq!(index.pages[1].image, "out-01.png");
+        assert_eq!(index.pages[1].texture_width, 30.0);
+
+        let ids = index
+            .sprites
+            .iter()
+            .map(|sprite| (sprite.id, sprite.page))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(ids, vec![(0, 0), (1, 1), (2, 0)]);
+
+        let found = index.find("a").expect("sprite should be in the index");
+        assert_eq!((found.page, found.x, found.y), (0, 10.0, 0.0));
+        assert!(index.find("d").is_none());
+    }
+}
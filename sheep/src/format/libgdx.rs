@@ -0,0 +1,283 @@
+use super::{trim_info, MultiFormat};
+use std::fmt::Write;
+use {SpriteAnchor, TrimInfo};
+
+/// Writes the libGDX `TextureAtlas` text format, which is also understood by
+/// the Spine runtimes. All sheets returned by `pack` are written into the
+/// same atlas, one page per sheet.
+pub struct LibGdxFormat;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelFormat {
+    Alpha,
+    Intensity,
+    LuminanceAlpha,
+    Rgb565,
+    Rgba4444,
+    Rgb888,
+    Rgba8888,
+}
+
+impl PixelFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            PixelFormat::Alpha => "Alpha",
+            PixelFormat::Intensity => "Intensity",
+            PixelFormat::LuminanceAlpha => "LuminanceAlpha",
+            PixelFormat::Rgb565 => "RGB565",
+            PixelFormat::Rgba4444 => "RGBA4444",
+            PixelFormat::Rgb888 => "RGB888",
+            PixelFormat::Rgba8888 => "RGBA8888",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+    MipMap,
+    MipMapNearestNearest,
+    MipMapLinearNearest,
+    MipMapNearestLinear,
+    MipMapLinearLinear,
+}
+
+impl TextureFilter {
+    fn as_str(self) -> &'static str {
+        match self {
+            TextureFilter::Nearest => "Nearest",
+            TextureFilter::Linear => "Linear",
+            TextureFilter::MipMap => "MipMap",
+            TextureFilter::MipMapNearestNearest => "MipMapNearestNearest",
+            TextureFilter::MipMapLinearNearest => "MipMapLinearNearest",
+            TextureFilter::MipMapNearestLinear => "MipMapNearestLinear",
+            TextureFilter::MipMapLinearLinear => "MipMapLinearLinear",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureRepeat {
+    None,
+    X,
+    Y,
+    XY,
+}
+
+impl TextureRepeat {
+    fn as_str(self) -> &'static str {
+        match self {
+            TextureRepeat::None => "none",
+            TextureRepeat::X => "x",
+            TextureRepeat::Y => "y",
+            TextureRepeat::XY => "xy",
+        }
+    }
+}
+
+/// `pages` are the image filenames of the sheets, relative to the atlas
+/// file, and `names` are the sprite names, indexed by sprite id.
+#[derive(Clone, Debug)]
+pub struct LibGdxOptions {
+    pages: Vec<String>,
+    names: Vec<String>,
+    trims: Vec<TrimInfo>,
+    format: PixelFormat,
+    min_filter: TextureFilter,
+    mag_filter: TextureFilter,
+    repeat: TextureRepeat,
+}
+
+impl LibGdxOptions {
+    pub fn new(pages: Vec<String>, names: Vec<String>) -> Self {
+        LibGdxOptions {
+            pages,
+            names,
+            trims: Vec::new(),
+            format: PixelFormat::Rgba8888,
+            min_filter: TextureFilter::Nearest,
+            mag_filter: TextureFilter::Nearest,
+            repeat: TextureRepeat::None,
+        }
+    }
+
+    /// How the sprites were trimmed, indexed by sprite id, so `orig` and
+    /// `offset` describe the untrimmed images. See `sheep::trim_info`.
+    pub fn trims(mut self, trims: Vec<TrimInfo>) -> Self {
+        self.trims = trims;
+        self
+    }
+
+    pub fn format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn filter(mut self, min_filter: TextureFilter, mag_filter: TextureFilter) -> Self {
+        self.min_filter = min_filter;
+        self.mag_filter = mag_filter;
+        self
+    }
+
+    pub fn repeat(mut self, repeat: TextureRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+impl MultiFormat for LibGdxFormat {
+    type Data = String;
+    type Options = LibGdxOptions;
+
+    fn encode(sheets: &[((u32, u32), &[SpriteAnchor])], options: Self::Options) -> Self::Data {
+        let mut out = String::new();
+
+        for (i, (dimensions, sprites)) in sheets.iter().enumerate() {
+            // Every page starts with an empty line
+            writeln!(out).unwrap();
+            writeln!(out, "{}", options.pages[i]).unwrap();
+            writeln!(out, "size: {},{}", dimensions.0, dimensions.1).unwrap();
+            writeln!(out, "format: {}", options.format.as_str()).unwrap();
+            writeln!(
+                out,
+                "filter: {},{}",
+                options.min_filter.as_str(),
+                options.mag_filter.as_str()
+            )
+            .unwrap();
+            writeln!(out, "repeat: {}", options.repeat.as_str()).unwrap();
+
+            for anchor in sprites.iter() {
+                let (name, index) = split_index(&options.names[anchor.id]);
+
+                writeln!(out, "{}", name).unwrap();
+                writeln!(out, "  rotate: false").unwrap();
+                writeln!(out, "  xy: {}, {}", anchor.position.0, anchor.position.1).unwrap();
+                writeln!(
+                    out,
+                    "  size: {}, {}",
+                    anchor.dimensions.0, anchor.dimensions.1
+                )
+                .unwrap();
+                // The offset is measured from the bottom left corner of the
+                // untrimmed image, since libGDX uses y-up coordinates
+                let trim = trim_info(&options.trims, anchor);
+                let offset_y = trim
+                    .original
+                    .1
+                    .saturating_sub(trim.offset.1 + anchor.dimensions.1);
+                writeln!(out, "  orig: {}, {}", trim.original.0, trim.original.1).unwrap();
+                writeln!(out, "  offset: {}, {}", trim.offset.0, offset_y).unwrap();
+                writeln!(out, "  index: {}", index).unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+// Like the libGDX texture packer, we treat a numeric suffix after an
+// underscore as the frame index of an animation, e.g. `run_03`.
+fn split_index(name: &str) -> (&str, i64) {
+    if let Some(split) = name.rfind('_') {
+        if let Ok(index) = name[split + 1..].parse::<i64>() {
+            if split > 0 && index >= 0 {
+                return (&name[..split], index);
+            }
+        }
+    }
+
+    (name, -1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_frame_index() {
+        assert_eq!(split_index("run_03"), ("run", 3));
+        assert_eq!(split_index("player_run_12"), ("player_run", 12));
+        assert_eq!(split_index("idle"), ("idle", -1));
+        assert_eq!(split_index("idle_"), ("idle_", -1));
+        assert_eq!(split_index("_1"), ("_1", -1));
+    }
+
+    #[test]
+    fn encode_multiple_pages() {
+        let first = vec![SpriteAnchor::new(0, (0, 0), (10, 10))];
+        let second = vec![SpriteAnchor::new(1, (0, 0), (20, 10))];
+        let sheets = vec![((10, 10), first.as_slice()), ((20, 10), second.as_slice())];
+
+        let options = LibGdxOptions::new(
+            vec![String::from("out-00.png"), String::from("out-01.png")],
+            vec![String::from("a"), String::from("b_1")],
+        );
+        let atlas = LibGdxFormat::encode(&sheets, options);
+
+        let expected = "
+out-00.png
+size: 10,10
+format: RGBA8888
+filter: Nearest,Nearest
+repeat: none
+a
+  rotate: false
+  xy: 0, 0
+  size: 10, 10
+  orig: 10, 10
+  offset: 0, 0
+  index: -1
+
+out-01.png
+size: 20,10
+format: RGBA8888
+filter: Nearest,Nearest
+repeat: none
+b
+  rotate: false
+  xy: 0, 0
+  size: 20, 10
+  orig: 20, 10
+  offset: 0, 0
+  index: 1
+";
+        assert_eq!(atlas, expected);
+    }
+
+    #[test]
+    fn encode_trimmed() {
+        let sprites = vec![SpriteAnchor::new(0, (0, 0), (6, 4))];
+        let sheets = vec![((6, 4), sprites.as_slice())];
+
+        let trim = TrimInfo {
+            offset: (3, 1),
+            original: (10, 8),
+            trimmed: (6, 4),
+        };
+        let options = LibGdxOptions::new(vec![String::from("out.png")], vec![String::from("a")])
+            .trims(vec![trim]);
+        let atlas = LibGdxFormat::encode(&sheets, options);
+
+        assert!(atlas.contains("  size: 6, 4\n  orig: 10, 8\n  offset: 3, 3\n"));
+    }
+
+    #[test]
+    fn encode_trimmed_downscaled() {
+        // The trimmed sprite was 6x4 before the packer scaled it down
+        let sprites = vec![SpriteAnchor::new(0, (0, 0), (3, 2))];
+        let sheets = vec![((3, 2), sprites.as_slice())];
+
+        let trim = TrimInfo {
+            offset: (3, 1),
+            original: (10, 8),
+            trimmed: (6, 4),
+        };
+        let options = LibGdxOptions::new(vec![String::from("out.png")], vec![String::from("a")])
+            .trims(vec![trim]);
+        let atlas = LibGdxFormat::encode(&sheets, options);
+
+        assert!(atlas.contains("  size: 3, 2\n  orig: 5, 4\n  offset: 2, 1\n"));
+    }
+}
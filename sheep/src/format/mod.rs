@@ -1,10 +1,23 @@
 #[cfg(feature = "amethyst")]
 pub mod amethyst;
 
+#[cfg(feature = "amethyst")]
+pub mod index;
+
 #[cfg(feature = "amethyst")]
 pub mod named;
 
-use SpriteAnchor;
+#[cfg(feature = "amethyst")]
+pub mod uv;
+
+pub mod cocos2d;
+pub mod css;
+pub mod libgdx;
+pub mod rust;
+pub mod sparrow;
+pub mod unity;
+
+use {SpriteAnchor, TrimInfo};
 
 pub trait Format {
     type Data;
@@ -16,3 +29,31 @@ pub trait Format {
         options: Self::Options,
     ) -> Self::Data;
 }
+
+/// Like `Format`, but encodes all sheets returned by a single call to `pack`
+/// at once. Each sheet is passed as its dimensions and sprite anchors.
+pub trait MultiFormat {
+    type Data;
+    type Options;
+
+    fn encode(sheets: &[((u32, u32), &[SpriteAnchor])], options: Self::Options) -> Self::Data;
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// How the sprite was trimmed, or an untrimmed info if the sprites weren't
+// trimmed at all. Packers may have scaled the sprite down, so the info is
+// scaled to the dimensions of the anchor.
+fn trim_info(trims: &[TrimInfo], anchor: &SpriteAnchor) -> TrimInfo {
+    trims
+        .get(anchor.id)
+        .map(|trim| trim.scaled(anchor.dimensions))
+        .unwrap_or_else(|| TrimInfo::untrimmed(anchor.dimensions))
+}
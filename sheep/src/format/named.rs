@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use super::amethyst::anchor;
 use super::Format;
 use SpriteAnchor;
 
@@ -17,14 +20,14 @@ pub struct NamedSpritePosition {
 
 impl From<(&SpriteAnchor, String)> for NamedSpritePosition {
     fn from(anchor: (&SpriteAnchor, String)) -> NamedSpritePosition {
-        return NamedSpritePosition {
+        NamedSpritePosition {
             name: anchor.1,
             x: anchor.0.position.0 as f32,
             y: anchor.0.position.1 as f32,
             width: anchor.0.dimensions.0 as f32,
             height: anchor.0.dimensions.1 as f32,
             offsets: None,
-        };
+        }
     }
 }
 
@@ -38,6 +41,46 @@ pub struct SerializedNamedSpriteSheet {
     pub sprites: Vec<NamedSpritePosition>,
 }
 
+impl SerializedNamedSpriteSheet {
+    /// Looks up a sprite by name. Names are expected to be unique, which the
+    /// CLI ensures, so this is the only sprite with that name.
+    pub fn find(&self, name: &str) -> Option<&NamedSpritePosition> {
+        self.sprites.iter().find(|sprite| sprite.name == name)
+    }
+
+    /// Turns the sprite positions back into anchors for the sprites in
+    /// `names` that are also in this sheet, using their index in `names` as
+    /// the id. This matches the ids of the sprites when packing them again,
+    /// e.g. as a hint for `StableMaxrectsPacker`.
+    pub fn anchors_by_name(&self, names: &[String]) -> Vec<SpriteAnchor> {
+        // Keeps the first sprite of a name, like `find` does
+        let mut by_name = HashMap::with_capacity(self.sprites.len());
+        for sprite in &self.sprites {
+            by_name.entry(sprite.name.as_str()).or_insert(sprite);
+        }
+
+        names
+            .iter()
+            .enumerate()
+            .filter_map(|(id, name)| {
+                by_name
+                    .get(name.as_str())
+                    .map(|sprite| anchor(id, [sprite.x, sprite.y, sprite.width, sprite.height]))
+            })
+            .collect()
+    }
+
+    /// Turns the sprite positions back into anchors, using the position of
+    /// each sprite in `sprites` as its id.
+    pub fn anchors(&self) -> Vec<SpriteAnchor> {
+        self.sprites
+            .iter()
+            .enumerate()
+            .map(|(id, sprite)| anchor(id, [sprite.x, sprite.y, sprite.width, sprite.height]))
+            .collect()
+    }
+}
+
 impl Format for AmethystNamedFormat {
     type Data = SerializedNamedSpriteSheet;
     type Options = Vec<String>;
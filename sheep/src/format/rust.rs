@@ -0,0 +1,288 @@
+use super::Format;
+use std::collections::HashSet;
+use std::fmt::Write;
+use SpriteAnchor;
+
+/// Generates Rust source with a constant rect and UV for every sprite,
+/// plus an enum of all sprite names. The output is meant to be pulled into
+/// a module with `include!`, so sprites can be referred to as e.g.
+/// `Sprites::PlayerIdle`, checked at compile time.
+pub struct RustFormat;
+
+/// `names` are the sprite names, indexed by sprite id.
+#[derive(Clone, Debug)]
+pub struct RustOptions {
+    names: Vec<String>,
+    enum_name: String,
+}
+
+impl RustOptions {
+    pub fn new(names: Vec<String>) -> Self {
+        RustOptions {
+            names,
+            enum_name: String::from("Sprites"),
+        }
+    }
+
+    pub fn enum_name(mut self, enum_name: String) -> Self {
+        self.enum_name = enum_name;
+        self
+    }
+}
+
+impl Format for RustFormat {
+    type Data = String;
+    type Options = RustOptions;
+
+    fn encode(
+        dimensions: (u32, u32),
+        sprites: &[SpriteAnchor],
+        options: Self::Options,
+    ) -> Self::Data {
+        let enum_name = &options.enum_name;
+        let mut out = String::new();
+
+        // Generate identifiers up front, making sure they are unique even if
+        // different names end up with the same identifier
+        let mut taken = HashSet::new();
+        let idents = sprites
+            .iter()
+            .map(|anchor| {
+                let words = split_words(&options.names[anchor.id]);
+                let mut variant = words
+                    .iter()
+                    .map(|word| {
+                        let mut chars = word.chars();
+                        let first = chars.next().map(|c| c.to_ascii_uppercase());
+                        first.into_iter().chain(chars).collect::<String>()
+                    })
+                    .collect::<String>();
+                let mut constant = words.join("_").to_ascii_uppercase();
+
+                if variant.is_empty() || variant.starts_with(|c: char| c.is_ascii_digit()) {
+                    variant = format!("Sprite{}", variant);
+                    constant = format!("SPRITE_{}", constant)
+                        .trim_end_matches('_')
+                        .to_owned();
+                }
+
+                // `Self` is the only keyword that is valid camel case
+                if variant == "Self" {
+                    variant = String::from("SpriteSelf");
+                }
+
+                let (base_variant, base_constant) = (variant.clone(), constant.clone());
+                let mut suffix = 1;
+                while taken.contains(&variant) || taken.contains(&constant) {
+                    variant = format!("{}{}", base_variant, suffix);
+                    constant = format!("{}_{}", base_constant, suffix);
+                    suffix += 1;
+                }
+
+                taken.insert(variant.clone());
+                taken.insert(constant.clone());
+                (variant, constant)
+            })
+            .collect::<Vec<(String, String)>>();
+
+        writeln!(out, "// Generated by sheep, do not edit.").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "pub const TEXTURE_WIDTH: u32 = {};", dimensions.0).unwrap();
+        writeln!(out, "pub const TEXTURE_HEIGHT: u32 = {};", dimensions.1).unwrap();
+
+        for (anchor, (_, constant)) in sprites.iter().zip(&idents) {
+            let (x, y) = anchor.position;
+            let (width, height) = anchor.dimensions;
+            let uv = [
+                normalize(x, dimensions.0),
+                normalize(y, dimensions.1),
+                normalize(x + width, dimensions.0),
+                normalize(y + height, dimensions.1),
+            ];
+
+            writeln!(out).unwrap();
+            writeln!(
+                out,
+                "pub const {}_RECT: [u32; 4] = [{}, {}, {}, {}];",
+                constant, x, y, width, height
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "pub const {}_UV: [f32; 4] = [{:?}, {:?}, {:?}, {:?}];",
+                constant, uv[0], uv[1], uv[2], uv[3]
+            )
+            .unwrap();
+        }
+
+        writeln!(out).unwrap();
+        writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]").unwrap();
+        writeln!(out, "pub enum {} {{", enum_name).unwrap();
+        for (variant, _) in &idents {
+            writeln!(out, "    {},", variant).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(out, "impl {} {{", enum_name).unwrap();
+        writeln!(
+            out,
+            "    pub const ALL: [{}; {}] = [",
+            enum_name,
+            idents.len()
+        )
+        .unwrap();
+        for (variant, _) in &idents {
+            writeln!(out, "        {}::{},", enum_name, variant).unwrap();
+        }
+        writeln!(out, "    ];").unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(out, "    pub fn name(self) -> &'static str {{").unwrap();
+        writeln!(out, "        match self {{").unwrap();
+        for (anchor, (variant, _)) in sprites.iter().zip(&idents) {
+            writeln!(
+                out,
+                "            {}::{} => {:?},",
+                enum_name, variant, options.names[anchor.id]
+            )
+            .unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(out, "    /// `[x, y, width, height]` in pixels").unwrap();
+        writeln!(out, "    pub fn rect(self) -> [u32; 4] {{").unwrap();
+        writeln!(out, "        match self {{").unwrap();
+        for (variant, constant) in &idents {
+            writeln!(
+                out,
+                "            {}::{} => {}_RECT,",
+                enum_name, variant, constant
+            )
+            .unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+
+        writeln!(
+            out,
+            "    /// `[u0, v0, u1, v1]`, with the origin in the top left corner"
+        )
+        .unwrap();
+        writeln!(out, "    pub fn uv(self) -> [f32; 4] {{").unwrap();
+        writeln!(out, "        match self {{").unwrap();
+        for (variant, constant) in &idents {
+            writeln!(
+                out,
+                "            {}::{} => {}_UV,",
+                enum_name, variant, constant
+            )
+            .unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out, "}}").unwrap();
+
+        out
+    }
+}
+
+// Divides by the size of the sheet, except for empty sheets, which would
+// give NaN, and that isn't a valid float literal
+fn normalize(value: u32, size: u32) -> f32 {
+    if size == 0 {
+        return 0.0;
+    }
+
+    value as f32 / size as f32
+}
+
+// Splits a sprite name into words at anything that isn't alphanumeric, as
+// well as at lower to upper case transitions, e.g. `player/runLeft_01`
+// becomes `player`, `run`, `Left` and `01`.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !current.is_empty() {
+                words.push(current);
+                current = String::new();
+            }
+            previous_lower = false;
+            continue;
+        }
+
+        if c.is_ascii_uppercase() && previous_lower {
+            words.push(current);
+            current = String::new();
+        }
+
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        current.push(c);
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_names() {
+        assert_eq!(
+            split_words("player/runLeft_01"),
+            ["player", "run", "Left", "01"]
+        );
+        assert_eq!(split_words("idle"), ["idle"]);
+        assert_eq!(split_words("--"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn encode_identifiers() {
+        let sprites = vec![
+            SpriteAnchor::new(0, (0, 0), (10, 20)),
+            SpriteAnchor::new(1, (10, 0), (10, 10)),
+            SpriteAnchor::new(2, (0, 20), (5, 5)),
+            SpriteAnchor::new(3, (5, 20), (5, 5)),
+        ];
+        let names = vec![
+            String::from("player_idle"),
+            String::from("player-idle"),
+            String::from("01"),
+            String::from("self"),
+        ];
+
+        let source = RustFormat::encode((20, 40), &sprites, RustOptions::new(names));
+
+        assert!(source.contains("pub const PLAYER_IDLE_RECT: [u32; 4] = [0, 0, 10, 20];"));
+        assert!(source.contains("pub const PLAYER_IDLE_UV: [f32; 4] = [0.0, 0.0, 0.5, 0.5];"));
+        assert!(source.contains("pub const PLAYER_IDLE_1_RECT: [u32; 4] = [10, 0, 10, 10];"));
+        assert!(source.contains("pub const SPRITE_01_RECT"));
+        assert!(
+            source.contains("    PlayerIdle,\n    PlayerIdle1,\n    Sprite01,\n    SpriteSelf,\n")
+        );
+        assert!(source.contains("Sprites::PlayerIdle1 => \"player-idle\","));
+    }
+
+    #[test]
+    fn encode_empty_sheet() {
+        let sprites = vec![SpriteAnchor::new(0, (0, 0), (0, 0))];
+        let names = vec![String::from("empty")];
+
+        let source = RustFormat::encode((0, 0), &sprites, RustOptions::new(names));
+
+        assert!(source.contains("pub const EMPTY_UV: [f32; 4] = [0.0, 0.0, 0.0, 0.0];"));
+        assert!(!source.contains("NaN"));
+    }
+}
@@ -0,0 +1,132 @@
+use super::{escape_xml, trim_info, Format};
+use std::fmt::Write;
+use {SpriteAnchor, TrimInfo};
+
+/// Writes a Sparrow/Starling `TextureAtlas` XML file.
+pub struct SparrowFormat;
+
+/// `texture` is the filename of the sheet image, relative to the XML file,
+/// and `names` are the sprite names, indexed by sprite id.
+#[derive(Clone, Debug)]
+pub struct SparrowOptions {
+    texture: String,
+    names: Vec<String>,
+    trims: Vec<TrimInfo>,
+}
+
+impl SparrowOptions {
+    pub fn new(texture: String, names: Vec<String>) -> Self {
+        SparrowOptions {
+            texture,
+            names,
+            trims: Vec::new(),
+        }
+    }
+
+    /// How the sprites were trimmed, indexed by sprite id, so the frames
+    /// describe the untrimmed images. See `sheep::trim_info`.
+    pub fn trims(mut self, trims: Vec<TrimInfo>) -> Self {
+        self.trims = trims;
+        self
+    }
+}
+
+impl Format for SparrowFormat {
+    type Data = String;
+    type Options = SparrowOptions;
+
+    fn encode(
+        _dimensions: (u32, u32),
+        sprites: &[SpriteAnchor],
+        options: Self::Options,
+    ) -> Self::Data {
+        let SparrowOptions {
+            texture,
+            names,
+            trims,
+        } = options;
+        let mut out = String::new();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(
+            out,
+            r#"<TextureAtlas imagePath="{}">"#,
+            escape_xml(&texture)
+        )
+        .unwrap();
+
+        for anchor in sprites {
+            write!(
+                out,
+                r#"    <SubTexture name="{}" x="{}" y="{}" width="{}" height="{}""#,
+                escape_xml(&names[anchor.id]),
+                anchor.position.0,
+                anchor.position.1,
+                anchor.dimensions.0,
+                anchor.dimensions.1
+            )
+            .unwrap();
+
+            // The frame is only needed to restore trimmed sprites
+            let trim = trim_info(&trims, anchor);
+            if trim != TrimInfo::untrimmed(anchor.dimensions) {
+                write!(
+                    out,
+                    r#" frameX="-{}" frameY="-{}" frameWidth="{}" frameHeight="{}""#,
+                    trim.offset.0, trim.offset.1, trim.original.0, trim.original.1
+                )
+                .unwrap();
+            }
+
+            writeln!(out, "/>").unwrap();
+        }
+
+        writeln!(out, "</TextureAtlas>").unwrap();
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_sub_textures() {
+        let sprites = vec![
+            SpriteAnchor::new(0, (0, 0), (10, 20)),
+            SpriteAnchor::new(1, (10, 0), (5, 5)),
+        ];
+        let options = SparrowOptions::new(
+            String::from("out.png"),
+            vec![String::from("a"), String::from("b&c")],
+        );
+
+        let xml = SparrowFormat::encode((15, 20), &sprites, options);
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TextureAtlas imagePath="out.png">
+    <SubTexture name="a" x="0" y="0" width="10" height="20"/>
+    <SubTexture name="b&amp;c" x="10" y="0" width="5" height="5"/>
+</TextureAtlas>
+"#;
+
+        assert_eq!(xml, expected);
+    }
+
+    #[test]
+    fn encode_trimmed_sub_textures() {
+        let sprites = vec![SpriteAnchor::new(0, (0, 0), (10, 20))];
+        let trim = TrimInfo {
+            offset: (3, 2),
+            original: (15, 30),
+            trimmed: (10, 20),
+        };
+        let options =
+            SparrowOptions::new(String::from("out.png"), vec![String::from("a")]).trims(vec![trim]);
+
+        let xml = SparrowFormat::encode((10, 20), &sprites, options);
+        assert!(xml.contains(
+            r#"<SubTexture name="a" x="0" y="0" width="10" height="20" frameX="-3" frameY="-2" frameWidth="15" frameHeight="30"/>"#
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use super::Format;
+use std::fmt::Write;
+use std::hash::Hasher;
+use twox_hash::XxHash64;
+use SpriteAnchor;
+
+/// Writes the contents of a Unity `TextureImporter` `.meta` file that slices
+/// the sprite sheet into multiple sprites (`spriteMode: 2`).
+///
+/// The options are the names of the sprites, indexed by sprite id, just like
+/// for `AmethystNamedFormat`. The output should be saved next to the sheet
+/// image, e.g. `out.png.meta` for `out.png`.
+pub struct UnityFormat;
+
+impl Format for UnityFormat {
+    type Data = String;
+    type Options = Vec<String>;
+
+    fn encode(
+        dimensions: (u32, u32),
+        sprites: &[SpriteAnchor],
+        options: Self::Options,
+    ) -> Self::Data {
+        let mut out = String::new();
+
+        // Unity requires a guid for every asset. Deriving it from the
+        // contents keeps it stable when the same sheet is exported again.
+        let mut guid = XxHash64::with_seed(0);
+        guid.write_u32(dimensions.0);
+        guid.write_u32(dimensions.1);
+        for anchor in sprites {
+            guid.write(options[anchor.id].as_bytes());
+        }
+
+        writeln!(out, "fileFormatVersion: 2").unwrap();
+        writeln!(out, "guid: {}", hex_id(guid.finish(), sprites.len() as u64)).unwrap();
+        writeln!(out, "TextureImporter:").unwrap();
+        writeln!(out, "  serializedVersion: 9").unwrap();
+        writeln!(out, "  mipmaps:").unwrap();
+        writeln!(out, "    enableMipMap: 0").unwrap();
+        writeln!(out, "  textureType: 8").unwrap();
+        writeln!(out, "  textureShape: 1").unwrap();
+        writeln!(out, "  spriteMode: 2").unwrap();
+        writeln!(out, "  alphaIsTransparency: 1").unwrap();
+        writeln!(out, "  spritePixelsToUnits: 100").unwrap();
+        writeln!(out, "  spriteSheet:").unwrap();
+        writeln!(out, "    serializedVersion: 2").unwrap();
+
+        if sprites.is_empty() {
+            writeln!(out, "    sprites: []").unwrap();
+        } else {
+            writeln!(out, "    sprites:").unwrap();
+        }
+
+        for anchor in sprites {
+            let name = &options[anchor.id];
+
+            // Unity's origin is in the bottom left corner of the texture
+            let y = dimensions.1 - anchor.position.1 - anchor.dimensions.1;
+
+            let mut sprite_id = XxHash64::with_seed(1);
+            sprite_id.write(name.as_bytes());
+
+            writeln!(out, "    - serializedVersion: 2").unwrap();
+            writeln!(out, "      name: {}", yaml_string(name)).unwrap();
+            writeln!(out, "      rect:").unwrap();
+            writeln!(out, "        serializedVersion: 2").unwrap();
+            writeln!(out, "        x: {}", anchor.position.0).unwrap();
+            writeln!(out, "        y: {}", y).unwrap();
+            writeln!(out, "        width: {}", anchor.dimensions.0).unwrap();
+            writeln!(out, "        height: {}", anchor.dimensions.1).unwrap();
+            writeln!(out, "      alignment: 0").unwrap();
+            writeln!(out, "      pivot: {{x: 0.5, y: 0.5}}").unwrap();
+            writeln!(out, "      border: {{x: 0, y: 0, z: 0, w: 0}}").unwrap();
+            writeln!(out, "      outline: []").unwrap();
+            writeln!(out, "      physicsShape: []").unwrap();
+            writeln!(out, "      tessellationDetail: 0").unwrap();
+            writeln!(out, "      bones: []").unwrap();
+            writeln!(
+                out,
+                "      spriteID: {}",
+                hex_id(sprite_id.finish(), anchor.id as u64)
+            )
+            .unwrap();
+            writeln!(out, "      vertices: []").unwrap();
+            writeln!(out, "      indices: ").unwrap();
+            writeln!(out, "      edges: []").unwrap();
+            writeln!(out, "      weights: []").unwrap();
+        }
+
+        writeln!(out, "    outline: []").unwrap();
+        writeln!(out, "    physicsShape: []").unwrap();
+        writeln!(out, "    bones: []").unwrap();
+        writeln!(out, "    spriteID: ").unwrap();
+        writeln!(out, "    vertices: []").unwrap();
+        writeln!(out, "    indices: ").unwrap();
+        writeln!(out, "    edges: []").unwrap();
+        writeln!(out, "    weights: []").unwrap();
+        writeln!(out, "  spritePackingTag: ").unwrap();
+        writeln!(out, "  userData: ").unwrap();
+        writeln!(out, "  assetBundleName: ").unwrap();
+        writeln!(out, "  assetBundleVariant: ").unwrap();
+
+        out
+    }
+}
+
+// Unity ids are 128 bit, written as 32 hex digits
+fn hex_id(high: u64, low: u64) -> String {
+    let mut low_hash = XxHash64::with_seed(high);
+    low_hash.write_u64(low);
+    format!("{:016x}{:016x}", high, low_hash.finish())
+}
+
+// Quotes names that YAML would otherwise read as something other than a
+// plain string, or that span several lines. Only double quoted strings can
+// escape line breaks, so those are used.
+fn yaml_string(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.trim() != value
+        || value.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        || value.ends_with(':')
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.contains(char::is_control);
+
+    if !needs_quotes {
+        return value.to_owned();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => write!(quoted, "\\x{:02x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flips_y_axis() {
+        let sprites = vec![
+            SpriteAnchor::new(0, (0, 0), (10, 20)),
+            SpriteAnchor::new(1, (10, 0), (10, 10)),
+        ];
+        let names = vec![String::from("a"), String::from("b")];
+
+        let meta = UnityFormat::encode((20, 30), &sprites, names);
+        let ys = meta
+            .lines()
+            .filter(|line| line.starts_with("        y: "))
+            .collect::<Vec<&str>>();
+
+        assert_eq!(ys, vec!["        y: 10", "        y: 20"]);
+    }
+
+    #[test]
+    fn quotes_names() {
+        assert_eq!(yaml_string("player_idle"), "player_idle");
+        assert_eq!(yaml_string("it's"), "it's");
+        assert_eq!(yaml_string("#1"), r##""#1""##);
+        assert_eq!(yaml_string("it's: here"), r#""it's: here""#);
+        assert_eq!(yaml_string(""), r#""""#);
+        assert_eq!(yaml_string("idle:"), r#""idle:""#);
+        assert_eq!(yaml_string("two\nlines\r"), r#""two\nlines\r""#);
+        assert_eq!(yaml_string("say \"hi\"\\"), "say \"hi\"\\");
+        assert_eq!(yaml_string("\"hi\"\\"), r#""\"hi\"\\""#);
+        assert_eq!(yaml_string("bell\u{7}"), r#""bell\x07""#);
+
+        for indicator in &["-", "?", "&", "*", "!", "|", ">", "%", "@", "`"] {
+            let name = format!("{}name", indicator);
+            assert_eq!(yaml_string(&name), format!("\"{}\"", name));
+        }
+    }
+}
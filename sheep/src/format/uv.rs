@@ -0,0 +1,157 @@
+use super::Format;
+use SpriteAnchor;
+
+/// Like `AmethystFormat`, but with normalized texture coordinates instead of
+/// pixel rects, so they can be handed to the GPU directly.
+pub struct UvFormat;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UvOrigin {
+    /// `v` grows downwards, like the pixel rows of the image
+    TopLeft,
+    /// `v` grows upwards, as expected by e.g. OpenGL
+    BottomLeft,
+}
+
+#[derive(Clone, Debug)]
+pub struct UvOptions {
+    names: Option<Vec<String>>,
+    inset: f32,
+    origin: UvOrigin,
+}
+
+impl Default for UvOptions {
+    fn default() -> Self {
+        UvOptions {
+            names: None,
+            inset: 0.0,
+            origin: UvOrigin::TopLeft,
+        }
+    }
+}
+
+impl UvOptions {
+    /// Include the sprite names, indexed by sprite id, in the output.
+    pub fn names(mut self, names: Vec<String>) -> Self {
+        self.names = Some(names);
+        self
+    }
+
+    /// Moves every edge of the sprite inwards by the given amount of texels.
+    /// An inset of `0.5` samples the center of the border texels, which
+    /// avoids bleeding from neighbouring sprites when filtering.
+    pub fn inset(mut self, texels: f32) -> Self {
+        self.inset = texels;
+        self
+    }
+
+    pub fn origin(mut self, origin: UvOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+/// `(u0, v0)` is the corner with the smaller coordinates and `(u1, v1)` the
+/// one with the larger, regardless of the origin.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct UvSpritePosition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SerializedUvSpriteSheet {
+    pub texture_width: f32,
+    pub texture_height: f32,
+    pub sprites: Vec<UvSpritePosition>,
+}
+
+impl Format for UvFormat {
+    type Data = SerializedUvSpriteSheet;
+    type Options = UvOptions;
+
+    fn encode(
+        dimensions: (u32, u32),
+        sprites: &[SpriteAnchor],
+        options: Self::Options,
+    ) -> Self::Data {
+        let width = dimensions.0 as f32;
+        let height = dimensions.1 as f32;
+        let inset = options.inset;
+
+        let sprite_positions = sprites
+            .iter()
+            .map(|anchor| {
+                let min_x = anchor.position.0 as f32 + inset;
+                let min_y = anchor.position.1 as f32 + inset;
+                let max_x = (anchor.position.0 + anchor.dimensions.0) as f32 - inset;
+                let max_y = (anchor.position.1 + anchor.dimensions.1) as f32 - inset;
+
+                let (v0, v1) = match options.origin {
+                    UvOrigin::TopLeft => (min_y / height, max_y / height),
+                    UvOrigin::BottomLeft => ((height - max_y) / height, (height - min_y) / height),
+                };
+
+                UvSpritePosition {
+                    name: options.names.as_ref().map(|names| names[anchor.id].clone()),
+                    u0: min_x / width,
+                    v0,
+                    u1: max_x / width,
+                    v1,
+                }
+            })
+            .collect::<Vec<UvSpritePosition>>();
+
+        SerializedUvSpriteSheet {
+            texture_width: width,
+            texture_height: height,
+            sprites: sprite_positions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_origins() {
+        let sprites = vec![SpriteAnchor::new(0, (0, 0), (50, 25))];
+
+        let top_left = UvFormat::encode((100, 100), &sprites, UvOptions::default());
+        let sprite = &top_left.sprites[0];
+        assert_eq!(
+            (sprite.u0, sprite.v0, sprite.u1, sprite.v1),
+            (0.0, 0.0, 0.5, 0.25)
+        );
+
+        let options = UvOptions::default().origin(UvOrigin::BottomLeft);
+        let bottom_left = UvFormat::encode((100, 100), &sprites, options);
+        let sprite = &bottom_left.sprites[0];
+        assert_eq!(
+            (sprite.u0, sprite.v0, sprite.u1, sprite.v1),
+            (0.0, 0.75, 0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn encode_half_texel_inset() {
+        let sprites = vec![SpriteAnchor::new(0, (2, 4), (4, 2))];
+        let options = UvOptions::default()
+            .names(vec![String::from("a")])
+            .inset(0.5);
+
+        let sheet = UvFormat::encode((8, 8), &sprites, options);
+        let sprite = &sheet.sprites[0];
+
+        assert_eq!(sprite.name, Some(String::from("a")));
+        assert_eq!(
+            (sprite.u0, sprite.v0, sprite.u1, sprite.v1),
+            (2.5 / 8.0, 4.5 / 8.0, 5.5 / 8.0, 5.5 / 8.0)
+        );
+    }
+}
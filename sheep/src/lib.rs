@@ -5,33 +5,63 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(feature = "rayon")]
+extern crate rayon;
 extern crate smallvec;
 extern crate twox_hash;
 
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
+
+mod debug;
 mod format;
 mod pack;
+mod slice;
 mod sprite;
 
 pub use {
-    format::Format,
+    debug::{debug_overlay, DebugOptions},
+    format::{
+        cocos2d::{Cocos2dFormat, Cocos2dOptions},
+        css::{CssFormat, CssOptions, CssSpriteSheet},
+        libgdx::{LibGdxFormat, LibGdxOptions, PixelFormat, TextureFilter, TextureRepeat},
+        rust::{RustFormat, RustOptions},
+        sparrow::{SparrowFormat, SparrowOptions},
+        unity::UnityFormat,
+        Format, MultiFormat,
+    },
     pack::{
-        maxrects::{MaxrectsOptions, MaxrectsPacker},
-        simple::SimplePacker,
-        Packer, PackerResult,
+        maxrects::{
+            MaxrectsOptions, MaxrectsPacker, OversizedPolicy, StableMaxrectsOptions,
+            StableMaxrectsPacker,
+        },
+        simple::{SimpleOptions, SimplePacker, SimpleStrategy},
+        validate::{validate, validate_padded, ValidationError},
+        PackError, Packer, PackerResult,
     },
-    sprite::{InputSprite, Sprite, SpriteAnchor, SpriteData},
+    slice::{detect_sprites, slice_grid, GridOptions, SliceError},
+    sprite::{InputSprite, Sprite, SpriteAnchor, SpriteData, TrimInfo, UnpackError},
 };
 
 #[cfg(feature = "amethyst")]
 pub use format::amethyst::{AmethystFormat, SerializedSpriteSheet, SpritePosition};
 #[cfg(feature = "amethyst")]
+pub use format::index::{
+    IndexFormat, IndexOptions, IndexPage, IndexedSpritePosition, SerializedAtlasIndex,
+};
+#[cfg(feature = "amethyst")]
 pub use format::named::{AmethystNamedFormat, NamedSpritePosition, SerializedNamedSpriteSheet};
+#[cfg(feature = "amethyst")]
+pub use format::uv::{SerializedUvSpriteSheet, UvFormat, UvOptions, UvOrigin, UvSpritePosition};
 
-use sprite::{create_pixel_buffer, write_sprite};
+use sprite::{create_pixel_buffer, read_sprite, write_sprite};
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use smallvec::SmallVec;
 use std::collections::hash_map::HashMap;
-use std::hash::BuildHasherDefault;
+use std::hash::Hasher;
 use twox_hash::XxHash64;
 
 #[derive(Debug, Clone)]
@@ -40,20 +70,135 @@ pub struct SpriteSheet {
     pub stride: usize,
     pub dimensions: (u32, u32),
     anchors: Vec<SpriteAnchor>,
+    // Ids and sizes in bytes of the sprites that were written to this
+    // sheet, as they were passed to `pack`, so without aliases and before
+    // they were scaled down
+    packed: Vec<(usize, u64)>,
+    // How many sprites were passed to `pack`
+    input_len: usize,
+    stats: SheetStats,
 }
 
+impl SpriteSheet {
+    /// Where the sprites were placed on this sheet, including aliases,
+    /// sorted by sprite id.
+    pub fn anchors(&self) -> &[SpriteAnchor] {
+        &self.anchors
+    }
+
+    /// How well the space on this sheet is used.
+    pub fn stats(&self) -> SheetStats {
+        self.stats
+    }
+
+    /// `pack` only sees the sprites after trimming, so the bytes trimming
+    /// saved are only part of the stats after calling this with the input
+    /// sprites as they were before `trim`.
+    ///
+    /// Panics if `untrimmed` doesn't have as many sprites as were packed.
+    pub fn record_trimming(&mut self, untrimmed: &[InputSprite]) {
+        assert_eq!(
+            untrimmed.len(),
+            self.input_len,
+            "record_trimming needs the untrimmed versions of all {} sprites that were packed",
+            self.input_len
+        );
+
+        self.stats.trimmed_bytes = self
+            .packed
+            .iter()
+            .map(|(id, bytes)| (untrimmed[*id].bytes.len() as u64).saturating_sub(*bytes))
+            .sum();
+    }
+}
+
+/// Statistics about a single sheet, see `SpriteSheet::stats`. Areas are
+/// given in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SheetStats {
+    /// Sprites on the sheet, including aliases.
+    pub sprites: usize,
+    /// Sprites that weren't packed because they are duplicates of another
+    /// sprite on the sheet, and share its pixels instead.
+    pub aliases: usize,
+    pub area: u64,
+    /// Area covered by sprites, not counting aliases twice.
+    pub used_area: u64,
+    /// Bytes that trimming removed from the sprites on the sheet, see
+    /// `SpriteSheet::record_trimming`.
+    pub trimmed_bytes: u64,
+}
+
+impl SheetStats {
+    /// The share of the sheet covered by sprites, between 0 and 1.
+    pub fn occupancy(&self) -> f64 {
+        if self.area == 0 {
+            return 0.0;
+        }
+
+        self.used_area as f64 / self.area as f64
+    }
+
+    pub fn wasted_area(&self) -> u64 {
+        self.area - self.used_area
+    }
+}
+
+/// Packs the sprites into one or more sheets. The output only depends on
+/// the input sprites and options, so packing the same sprites again gives
+/// byte-identical sheets with the anchors in the same order.
+///
+/// Panics if the packer can't stay within the limits set in its options,
+/// see `try_pack`.
 pub fn pack<P: Packer>(
     input: Vec<InputSprite>,
     stride: usize,
     options: P::Options,
 ) -> Vec<SpriteSheet> {
-    let mut hashes: HashMap<&[u8], usize, BuildHasherDefault<XxHash64>> = Default::default();
-    let mut aliases: HashMap<usize, SmallVec<[usize; 1]>> = HashMap::with_capacity(input.len());
+    try_pack::<P>(input, stride, options).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Like `pack`, but reports sprites that are too large or need too many
+/// sheets for the limits set in the options of the packer.
+pub fn try_pack<P: Packer>(
+    input: Vec<InputSprite>,
+    stride: usize,
+    options: P::Options,
+) -> Result<Vec<SpriteSheet>, PackError> {
+    // Hashing the pixels is what takes time, so it's done up front, and the
+    // pixels are only compared for sprites with the same hash
+    #[cfg(feature = "rayon")]
+    let hashes = input.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let hashes = input.iter();
+    let hashes = hashes
+        .map(|sprite| {
+            let mut hasher = XxHash64::default();
+            hasher.write(&sprite.bytes);
+            hasher.finish()
+        })
+        .collect::<Vec<u64>>();
+
+    // `aliases[id]` lists all sprites with the same pixels as `id`, in input
+    // order, if `id` is the first of them, and is empty otherwise
+    let mut originals: HashMap<u64, SmallVec<[usize; 1]>> = HashMap::with_capacity(input.len());
+    let mut aliases: Vec<SmallVec<[usize; 1]>> = vec![SmallVec::new(); input.len()];
     for (id, sprite) in input.iter().enumerate() {
-        let alias_id = hashes.entry(sprite.bytes.as_slice()).or_insert(id);
-        aliases.entry(*alias_id).or_default().push(id);
+        let candidates = originals.entry(hashes[id]).or_default();
+        let alias_id = match candidates
+            .iter()
+            .find(|other| input[**other].bytes == sprite.bytes)
+        {
+            Some(other) => *other,
+            None => {
+                candidates.push(id);
+                id
+            }
+        };
+        aliases[alias_id].push(id);
     }
 
+    let input_len = input.len();
     let sprites = input
         .into_iter()
         .enumerate()
@@ -62,42 +207,65 @@ pub fn pack<P: Packer>(
     let sprite_data = sprites
         .iter()
         .enumerate()
-        .filter(|(id, _)| aliases.contains_key(id))
+        .filter(|(id, _)| !aliases[*id].is_empty())
         .map(|(_, it)| it.data)
         .collect::<Vec<SpriteData>>();
 
-    let packer_result = P::pack(&sprite_data, options);
+    let packer_result = P::try_pack(&sprite_data, options)?;
 
-    packer_result
-        .into_iter()
+    // Every sheet has its own buffer, so they can be written in parallel
+    #[cfg(feature = "rayon")]
+    let packer_result = packer_result.into_par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let packer_result = packer_result.into_iter();
+    let sheets = packer_result
         .map(|mut sheet| {
             let mut buffer = create_pixel_buffer(sheet.dimensions, stride);
             let mut aliased_anchors = Vec::<SpriteAnchor>::new();
+            let mut used_area = 0;
+            let mut packed = Vec::with_capacity(sheet.anchors.len());
             for anchor in &sheet.anchors {
+                used_area += u64::from(anchor.dimensions.0) * u64::from(anchor.dimensions.1);
+                packed.push((anchor.id, sprites[anchor.id].bytes.len() as u64));
                 write_sprite(
                     &mut buffer,
                     sheet.dimensions,
                     stride,
                     &sprites[anchor.id],
-                    &anchor,
+                    anchor,
                 );
                 aliased_anchors.extend(
-                    aliases[&anchor.id]
+                    aliases[anchor.id]
                         .iter()
                         .skip(1)
                         .map(|id| SpriteAnchor { id: *id, ..*anchor }),
                 );
             }
+            let alias_count = aliased_anchors.len();
             sheet.anchors.extend(aliased_anchors);
+            sheet.anchors.sort_by_key(|anchor| anchor.id);
+
+            let (width, height) = sheet.dimensions;
+            let stats = SheetStats {
+                sprites: sheet.anchors.len(),
+                aliases: alias_count,
+                area: u64::from(width) * u64::from(height),
+                used_area,
+                trimmed_bytes: 0,
+            };
 
             SpriteSheet {
                 bytes: buffer,
-                stride: stride,
+                stride,
                 dimensions: sheet.dimensions,
                 anchors: sheet.anchors,
+                packed,
+                input_len,
+                stats,
             }
         })
-        .collect()
+        .collect();
+    Ok(sheets)
 }
 
 pub fn encode<F>(sprite_sheet: &SpriteSheet, options: F::Options) -> F::Data
@@ -107,13 +275,59 @@ where
     F::encode(sprite_sheet.dimensions, &sprite_sheet.anchors, options)
 }
 
+pub fn encode_all<F>(sprite_sheets: &[SpriteSheet], options: F::Options) -> F::Data
+where
+    F: MultiFormat,
+{
+    let sheets = sprite_sheets
+        .iter()
+        .map(|sheet| (sheet.dimensions, sheet.anchors.as_slice()))
+        .collect::<Vec<_>>();
+
+    F::encode(&sheets, options)
+}
+
+/// Cuts the sprites described by `anchors` out of a sheet image, e.g. one
+/// that was loaded together with its metadata. The sprites are returned in
+/// the same order as the anchors. Fails if an anchor doesn't fit on the
+/// sheet, e.g. because the metadata belongs to a different image.
+pub fn unpack(
+    sheet: &InputSprite,
+    stride: usize,
+    anchors: &[SpriteAnchor],
+) -> Result<Vec<InputSprite>, UnpackError> {
+    anchors
+        .iter()
+        .map(|anchor| read_sprite(&sheet.bytes, sheet.dimensions, stride, anchor))
+        .collect()
+}
+
 pub fn trim(input: &[InputSprite], stride: usize, alpha_channel_index: usize) -> Vec<InputSprite> {
+    #[cfg(feature = "rayon")]
+    let input = input.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let input = input.iter();
     input
-        .iter()
         .map(|sprite| sprite.trimmed(stride, alpha_channel_index))
         .collect()
 }
 
+/// Where `trim` cuts every sprite out of its image, indexed like the input.
+/// Pass these to formats that can restore the untrimmed frames.
+pub fn trim_info(
+    input: &[InputSprite],
+    stride: usize,
+    alpha_channel_index: usize,
+) -> Vec<TrimInfo> {
+    #[cfg(feature = "rayon")]
+    let input = input.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let input = input.iter();
+    input
+        .map(|sprite| sprite.trim_info(stride, alpha_channel_index))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
@@ -133,10 +347,76 @@ mod tests {
         };
 
         let input = vec![sprite1.clone(), sprite1, sprite2];
-        let sheets = pack::<SimplePacker>(input, 4, ());
+        let sheets = pack::<SimplePacker>(input, 4, SimpleOptions::default());
 
         assert_eq!(sheets[0].anchors.len(), 3);
         assert_eq!(sheets[0].bytes.len(), 8);
+
+        let ids = sheets[0].anchors.iter().map(|a| a.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn downscale_test() {
+        let sprite = InputSprite {
+            bytes: vec![0, 0, 0, 255, 255, 255, 255, 255],
+            dimensions: (2, 1),
+        };
+        let options = MaxrectsOptions::default()
+            .max_width(1)
+            .max_height(1)
+            .oversized(OversizedPolicy::Downscale);
+
+        let sheets = try_pack::<MaxrectsPacker>(vec![sprite.clone()], 4, options).unwrap();
+        assert_eq!(sheets[0].dimensions, (1, 1));
+        assert_eq!(sheets[0].bytes, vec![127, 127, 127, 255]);
+
+        let options = options.max_pages(0);
+        assert!(try_pack::<MaxrectsPacker>(vec![sprite], 4, options).is_err());
+    }
+
+    #[test]
+    fn stats_test() {
+        let sprite = |width, height| InputSprite {
+            bytes: vec![255; width as usize * height as usize * 4],
+            dimensions: (width, height),
+        };
+        let padded = InputSprite {
+            bytes: vec![0, 0, 0, 0, 255, 255, 255, 255],
+            dimensions: (2, 1),
+        };
+
+        let input = vec![sprite(2, 2), sprite(2, 2), sprite(1, 2), padded];
+        let trimmed = trim(&input, 4, 3);
+        let mut sheets = pack::<SimplePacker>(trimmed, 4, SimpleOptions::default());
+        sheets[0].record_trimming(&input);
+
+        let stats = sheets[0].stats();
+        assert_eq!(stats.sprites, 4);
+        assert_eq!(stats.aliases, 1);
+        assert_eq!(stats.used_area, 4 + 2 + 1);
+        assert_eq!(
+            stats.area,
+            u64::from(sheets[0].dimensions.0 * sheets[0].dimensions.1)
+        );
+        assert_eq!(stats.wasted_area(), stats.area - 7);
+        assert_eq!(stats.trimmed_bytes, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "untrimmed versions of all 2 sprites")]
+    fn record_trimming_mismatch() {
+        let sprite = InputSprite {
+            bytes: vec![255; 4],
+            dimensions: (1, 1),
+        };
+
+        let mut sheets = pack::<SimplePacker>(
+            vec![sprite.clone(), sprite.clone()],
+            4,
+            SimpleOptions::default(),
+        );
+        sheets[0].record_trimming(&[sprite]);
     }
 
     #[test]
@@ -154,9 +434,43 @@ mod tests {
 
         let input = vec![sprite2.clone(), sprite1.clone(), sprite1, sprite2];
         let input = trim(input.as_slice(), 4, 3);
-        let sheets = pack::<SimplePacker>(input, 4, ());
+        let sheets = pack::<SimplePacker>(input, 4, SimpleOptions::default());
 
         assert_eq!(sheets[0].anchors.len(), 4);
         assert_eq!(sheets[0].bytes.len(), 4);
     }
+
+    #[cfg(feature = "amethyst")]
+    #[test]
+    fn round_trip_test() {
+        extern crate ron;
+
+        let input = (0..4u8)
+            .map(|i| InputSprite {
+                bytes: vec![i; (i as usize + 1) * 2 * 4],
+                dimensions: (i as u32 + 1, 2),
+            })
+            .collect::<Vec<InputSprite>>();
+        let names = (0..4).map(|i| i.to_string()).collect::<Vec<String>>();
+
+        let sheets = pack::<MaxrectsPacker>(input.clone(), 4, MaxrectsOptions::default());
+        let meta = encode::<AmethystNamedFormat>(&sheets[0], names);
+        let meta_str = ron::ser::to_string(&meta).expect("Failed to serialize meta");
+        let meta: SerializedNamedSpriteSheet =
+            ron::de::from_str(&meta_str).expect("Failed to deserialize meta");
+
+        let sheet = InputSprite {
+            bytes: sheets[0].bytes.clone(),
+            dimensions: sheets[0].dimensions,
+        };
+        let sprites = unpack(&sheet, 4, &meta.anchors()).expect("Failed to unpack sheet");
+
+        assert_eq!(sprites.len(), input.len());
+        assert_eq!(meta.find("2").map(|sprite| sprite.width), Some(3.0));
+        for (position, sprite) in meta.sprites.iter().zip(&sprites) {
+            let original = &input[position.name.parse::<usize>().unwrap()];
+            assert_eq!(sprite.dimensions, original.dimensions);
+            assert_eq!(sprite.bytes, original.bytes);
+        }
+    }
 }
@@ -1,11 +1,37 @@
-use {Packer, PackerResult, SpriteAnchor, SpriteData};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::cmp::{max, min};
+use std::collections::{HashMap, VecDeque};
+use {PackError, Packer, PackerResult, SpriteAnchor, SpriteData};
 
 pub struct MaxrectsPacker;
 
+/// Like `MaxrectsPacker`, but sprites are kept at the positions they had in
+/// a previous sheet as long as they still fit there, so adding or changing
+/// a few sprites doesn't move all the others. Only new and resized sprites,
+/// and those whose old spot is taken, are placed into the remaining space.
+pub struct StableMaxrectsPacker;
+
+/// What to do with sprites that are larger than the maximum sheet size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OversizedPolicy {
+    /// Every oversized sprite gets a sheet of its own, which is exactly as
+    /// large as the sprite.
+    Separate,
+    /// Oversized sprites are scaled down until they fit, keeping their
+    /// aspect ratio. Their anchors have the scaled dimensions.
+    Downscale,
+    /// Packing fails with `PackError::Oversized`.
+    Error,
+}
+
 #[derive(Copy, Clone)]
 pub struct MaxrectsOptions {
     max_width: u32,
     max_height: u32,
+    padding: u32,
+    max_pages: usize,
+    oversized: OversizedPolicy,
 }
 
 impl Default for MaxrectsOptions {
@@ -13,6 +39,9 @@ impl Default for MaxrectsOptions {
         MaxrectsOptions {
             max_width: 4096,
             max_height: 4096,
+            padding: 0,
+            max_pages: usize::MAX,
+            oversized: OversizedPolicy::Separate,
         }
     }
 }
@@ -27,49 +56,206 @@ impl MaxrectsOptions {
         self.max_height = height;
         self
     }
+
+    /// Transparent pixels to leave between sprites. There is no padding
+    /// along the edges of the sheet.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Fails with `PackError::TooManyPages` if the sprites need more
+    /// sheets, including those of oversized sprites. `pack` panics in that
+    /// case, use `try_pack` to handle it.
+    pub fn max_pages(mut self, pages: usize) -> Self {
+        self.max_pages = pages;
+        self
+    }
+
+    pub fn oversized(mut self, policy: OversizedPolicy) -> Self {
+        self.oversized = policy;
+        self
+    }
+}
+
+/// `previous` are the anchors of the sprites in the previous sheet, with
+/// their ids changed to match the ids of the sprites that are packed now,
+/// e.g. from `SerializedNamedSpriteSheet::anchors_by_name`.
+#[derive(Clone)]
+pub struct StableMaxrectsOptions {
+    options: MaxrectsOptions,
+    previous: Vec<SpriteAnchor>,
+}
+
+impl StableMaxrectsOptions {
+    pub fn new(options: MaxrectsOptions, previous: Vec<SpriteAnchor>) -> Self {
+        StableMaxrectsOptions { options, previous }
+    }
 }
 
 impl Packer for MaxrectsPacker {
     type Options = MaxrectsOptions;
 
     fn pack(sprites: &[SpriteData], options: MaxrectsOptions) -> Vec<PackerResult> {
-        let mut bins = Vec::new();
-        let mut oversized = Vec::new();
+        Self::try_pack(sprites, options).unwrap_or_else(|err| panic!("{}", err))
+    }
 
-        // First, filter out all oversized sprites
-        let mut sprites = sprites
-            .iter()
-            .enumerate()
-            .filter(|(i, sprite)| {
-                if sprite.dimensions.0 > options.max_width
-                    || sprite.dimensions.1 > options.max_height
-                {
-                    oversized.push(MaxRectsBin::oversized(sprite.dimensions, *i));
-                    false
-                } else {
-                    true
-                }
+    fn try_pack(
+        sprites: &[SpriteData],
+        options: MaxrectsOptions,
+    ) -> Result<Vec<PackerResult>, PackError> {
+        pack_bins(sprites, options, &[])
+    }
+}
+
+impl Packer for StableMaxrectsPacker {
+    type Options = StableMaxrectsOptions;
+
+    fn pack(sprites: &[SpriteData], options: StableMaxrectsOptions) -> Vec<PackerResult> {
+        Self::try_pack(sprites, options).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_pack(
+        sprites: &[SpriteData],
+        options: StableMaxrectsOptions,
+    ) -> Result<Vec<PackerResult>, PackError> {
+        pack_bins(sprites, options.options, &options.previous)
+    }
+}
+
+fn pack_bins(
+    sprites: &[SpriteData],
+    options: MaxrectsOptions,
+    previous: &[SpriteAnchor],
+) -> Result<Vec<PackerResult>, PackError> {
+    let mut bins = Vec::new();
+    let mut oversized = Vec::new();
+
+    // Padding is added to the right and bottom of every sprite, and the
+    // bins grow by the same amount so the padding of sprites along
+    // the right and bottom edge doesn't count against the max size
+    let padding = options.padding;
+    let max_width = options.max_width.saturating_add(padding);
+    let max_height = options.max_height.saturating_add(padding);
+
+    // First, deal with all oversized sprites
+    let mut padded = Vec::with_capacity(sprites.len());
+    for sprite in sprites {
+        let (width, height) = sprite.dimensions;
+        if width <= options.max_width && height <= options.max_height {
+            padded.push(SpriteData::new(
+                sprite.id,
+                (width + padding, height + padding),
+            ));
+            continue;
+        }
+
+        let fitted = fit_dimensions(sprite.dimensions, (options.max_width, options.max_height));
+        match (options.oversized, fitted) {
+            (OversizedPolicy::Separate, _) => {
+                let dimensions = (width + padding, height + padding);
+                oversized.push(MaxRectsBin::oversized(dimensions, sprite.id));
+            }
+            (OversizedPolicy::Downscale, Some((width, height))) => {
+                padded.push(SpriteData::new(
+                    sprite.id,
+                    (width + padding, height + padding),
+                ));
+            }
+            _ => {
+                return Err(PackError::Oversized {
+                    id: sprite.id,
+                    dimensions: sprite.dimensions,
+                })
+            }
+        }
+    }
+    let mut sprites = padded;
+
+    // Sprites that still fit at their previous position are placed
+    // there before anything else, and all others fill up the first bin
+    // around them. The previous anchors come from a file that might have
+    // been edited by hand, so those outside of the bin are ignored.
+    let mut previous_by_id = HashMap::with_capacity(previous.len());
+    for anchor in previous {
+        previous_by_id.entry(anchor.id).or_insert(anchor);
+    }
+
+    let mut first = MaxRectsBin::new(max_width, max_height);
+    sprites.retain(|sprite| {
+        let kept = previous_by_id
+            .get(&sprite.id)
+            .filter(|anchor| {
+                let (width, height) = anchor.dimensions;
+                (width + padding, height + padding) == sprite.dimensions
+            })
+            .filter(|anchor| {
+                let (x, y) = anchor.position;
+                let (width, height) = sprite.dimensions;
+                // Written this way around so it can't overflow
+                width <= max_width
+                    && x <= max_width - width
+                    && height <= max_height
+                    && y <= max_height - height
+            })
+            .map(|anchor| {
+                let (x, y) = anchor.position;
+                Rect::xywh(x, y, sprite.dimensions.0, sprite.dimensions.1)
             })
-            .map(|(_, sprite)| *sprite)
-            .collect::<Vec<_>>();
+            .filter(|rect| first.is_free(rect));
 
-        // Now, keep inserting as many as possible into each bin until
-        // all sprites have been placed. Since all oversized rects have
-        // already been filtered out, this will always terminate.
-        while !sprites.is_empty() {
-            let mut bin = MaxRectsBin::new(options.max_width, options.max_height);
-            sprites = bin.insert_sprites(&sprites);
-            bins.push(bin);
+        match kept {
+            Some(rect) => {
+                first.place_rect(rect, sprite.id);
+                false
+            }
+            None => true,
         }
+    });
 
-        bins.extend(oversized.into_iter());
-        let result = bins
-            .into_iter()
-            .map(|bin| bin.to_result())
-            .collect::<Vec<PackerResult>>();
+    if !first.used.is_empty() {
+        sprites = first.insert_sprites(&sprites);
+        bins.push(first);
+    }
 
-        result
+    // Now, keep inserting as many as possible into each bin until
+    // all sprites have been placed. Since all oversized rects have
+    // already been filtered out, this will always terminate.
+    while !sprites.is_empty() {
+        let mut bin = MaxRectsBin::new(max_width, max_height);
+        sprites = bin.insert_sprites(&sprites);
+        bins.push(bin);
     }
+
+    bins.extend(oversized);
+    if bins.len() > options.max_pages {
+        return Err(PackError::TooManyPages {
+            pages: bins.len(),
+            max_pages: options.max_pages,
+        });
+    }
+
+    Ok(bins
+        .iter()
+        .map(|bin| remove_padding(bin.to_result(), padding))
+        .collect::<Vec<PackerResult>>())
+}
+
+// The largest dimensions with the same aspect ratio that fit into `max`,
+// or nothing if not even a single pixel fits
+fn fit_dimensions(dimensions: (u32, u32), max: (u32, u32)) -> Option<(u32, u32)> {
+    let (width, height) = (u64::from(dimensions.0), u64::from(dimensions.1));
+    let (max_width, max_height) = (u64::from(max.0), u64::from(max.1));
+    if max_width == 0 || max_height == 0 {
+        return None;
+    }
+
+    let (width, height) = if width * max_height > height * max_width {
+        (max_width, (height * max_width / width).max(1))
+    } else {
+        ((width * max_height / height).max(1), max_height)
+    };
+    Some((width as u32, height as u32))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -90,15 +276,6 @@ impl Rect {
         }
     }
 
-    pub fn new(min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> Self {
-        Rect {
-            min_x,
-            min_y,
-            max_x,
-            max_y,
-        }
-    }
-
     pub fn contains(&self, other: &Rect) -> bool {
         self.min_x <= other.min_x
             && self.min_y <= other.min_y
@@ -114,50 +291,183 @@ impl Rect {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum ScoreResult {
-    NoFit,
-    FitFound(RectScore),
-}
-
 // NOTE(happenslol): The score represents the leftover
 // space in case of a placement, thus _lower is better_
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct RectScore {
     placement: Rect,
-    primary: i32,
-    secondary: i32,
+    free_id: usize,
+    primary: u32,
+    secondary: u32,
+}
+
+impl RectScore {
+    // Ties are broken by position, so the best score doesn't depend on the
+    // order of the free rects
+    fn key(&self) -> (u32, u32, u32, u32, usize) {
+        (
+            self.primary,
+            self.secondary,
+            self.placement.min_x,
+            self.placement.min_y,
+            self.free_id,
+        )
+    }
+}
+
+// Sprites of the same size always get the same score, so they're scored
+// once per size. `sprites` are the indices of the sprites that are left,
+// in input order, and `scores` the best scores among all free rects, best
+// first. There can be more free rects with worse scores that aren't kept.
+#[derive(Debug, Clone)]
+struct SizeGroup {
+    dimensions: (u32, u32),
+    sprites: VecDeque<usize>,
+    scores: Vec<RectScore>,
+}
+
+// How many scores are kept per size. A few more than the best one save
+// scoring all free rects again whenever the best free rect is taken.
+const CACHED_SCORES: usize = 8;
+
+// Roughly how many cells the grid has along each side
+const GRID_CELLS: u32 = 64;
+
+// The free rects of a bin. Every rect is also kept in the grid cells it
+// overlaps, so the rects around a position can be found without going
+// through all of them.
+#[derive(Debug, Clone)]
+struct FreeRects {
+    rects: Vec<(Rect, usize)>,
+    positions: HashMap<usize, usize>,
+    next_id: usize,
+    cell_size: u32,
+    columns: u32,
+    rows: u32,
+    cells: Vec<Vec<usize>>,
+}
+
+impl FreeRects {
+    pub fn new(width: u32, height: u32) -> Self {
+        let cell_size = max(width, height) / GRID_CELLS + 1;
+        let columns = width / cell_size + 1;
+        let rows = height / cell_size + 1;
+
+        FreeRects {
+            rects: Vec::new(),
+            positions: HashMap::new(),
+            next_id: 0,
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); (columns * rows) as usize],
+        }
+    }
+
+    pub fn get(&self, id: usize) -> &Rect {
+        &self.rects[self.positions[&id]].0
+    }
+
+    pub fn insert(&mut self, rect: Rect) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        for cell in self.cells_of(&rect) {
+            self.cells[cell].push(id);
+        }
+
+        self.positions.insert(id, self.rects.len());
+        self.rects.push((rect, id));
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> Rect {
+        let position = self.positions.remove(&id).expect("Unknown free rect");
+        let (rect, _) = self.rects.swap_remove(position);
+        if let Some(&(_, moved)) = self.rects.get(position) {
+            self.positions.insert(moved, position);
+        }
+
+        for cell in self.cells_of(&rect) {
+            self.cells[cell].retain(|it| *it != id);
+        }
+
+        rect
+    }
+
+    // The ids of all rects that share a cell with `rect`, which includes
+    // all rects intersecting it
+    pub fn near(&self, rect: &Rect) -> Vec<usize> {
+        let mut ids = self
+            .cells_of(rect)
+            .into_iter()
+            .flat_map(|cell| self.cells[cell].iter().cloned())
+            .collect::<Vec<usize>>();
+
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    pub fn contains(&self, rect: &Rect) -> bool {
+        // Every rect that contains `rect` covers its top left corner, so
+        // only the cell of that corner has to be checked
+        let corner = Rect::xywh(rect.min_x, rect.min_y, 1, 1);
+        self.cells_of(&corner)
+            .into_iter()
+            .flat_map(|cell| self.cells[cell].iter())
+            .any(|id| self.get(*id).contains(rect))
+    }
+
+    fn cells_of(&self, rect: &Rect) -> Vec<usize> {
+        let column = |x: u32| min(x / self.cell_size, self.columns - 1);
+        let row = |y: u32| min(y / self.cell_size, self.rows - 1);
+
+        // `max_x` and `max_y` are exclusive, but empty rects still get
+        // the cell of their position
+        let (min_column, max_column) = (
+            column(rect.min_x),
+            column(max(rect.min_x, rect.max_x.saturating_sub(1))),
+        );
+        let (min_row, max_row) = (
+            row(rect.min_y),
+            row(max(rect.min_y, rect.max_y.saturating_sub(1))),
+        );
+
+        let mut cells = Vec::new();
+        for y in min_row..=max_row {
+            for x in min_column..=max_column {
+                cells.push((y * self.columns + x) as usize);
+            }
+        }
+
+        cells
+    }
 }
 
 #[derive(Debug, Clone)]
 struct MaxRectsBin {
-    bin_width: u32,
-    bin_height: u32,
     used: Vec<(Rect, usize)>,
-    free: Vec<Rect>,
-    oversized: bool,
+    free: FreeRects,
 }
 
 impl MaxRectsBin {
     pub fn new(width: u32, height: u32) -> Self {
+        let mut free = FreeRects::new(width, height);
+        free.insert(Rect::xywh(0, 0, width, height));
+
         MaxRectsBin {
-            bin_width: width,
-            bin_height: height,
             used: Vec::new(),
-            free: vec![Rect::xywh(0, 0, width, height)],
-            oversized: false,
+            free,
         }
     }
 
-    pub fn oversized(dimensions: (u32, u32), index: usize) -> Self {
+    pub fn oversized(dimensions: (u32, u32), id: usize) -> Self {
         let used_rect = Rect::xywh(0, 0, dimensions.0, dimensions.1);
 
         MaxRectsBin {
-            bin_width: dimensions.0,
-            bin_height: dimensions.1,
-            used: vec![(used_rect, index)],
-            free: vec![],
-            oversized: true,
+            used: vec![(used_rect, id)],
+            free: FreeRects::new(0, 0),
         }
     }
 
@@ -191,167 +501,294 @@ impl MaxRectsBin {
     }
 
     pub fn insert_sprites(&mut self, sprites: &[SpriteData]) -> Vec<SpriteData> {
-        let mut sprites = sprites.iter().cloned().collect::<Vec<SpriteData>>();
-        let mut placed = Vec::new();
+        let mut groups = Vec::<SizeGroup>::new();
+        let mut group_ids = HashMap::<(u32, u32), usize>::new();
+        for (index, sprite) in sprites.iter().enumerate() {
+            let id = *group_ids.entry(sprite.dimensions).or_insert_with(|| {
+                groups.push(SizeGroup {
+                    dimensions: sprite.dimensions,
+                    sprites: VecDeque::new(),
+                    scores: Vec::new(),
+                });
+                groups.len() - 1
+            });
+            groups[id].sprites.push_back(index);
+        }
+
+        {
+            #[cfg(feature = "rayon")]
+            let scores = groups.par_iter_mut();
+            #[cfg(not(feature = "rayon"))]
+            let scores = groups.iter_mut();
+            let bin = &*self;
+            scores.for_each(|group| group.scores = bin.score_rect(group.dimensions));
+        }
 
-        while !sprites.is_empty() {
-            // Score all rects and sort them by their score, best score first
-            let mut placeable = sprites
+        loop {
+            // Take the sprite with the best score, and the first one in
+            // input order if multiple sprites have the same score
+            let best = groups
                 .iter()
-                .filter_map(|sprite| {
-                    match self.score_rect(sprite.dimensions.0, sprite.dimensions.1) {
-                        ScoreResult::NoFit => None,
-                        ScoreResult::FitFound(score) => Some((score, *sprite)),
-                    }
+                .enumerate()
+                .filter_map(|(id, group)| {
+                    let score = group.scores.first()?;
+                    let index = *group.sprites.front()?;
+                    Some(((score.primary, score.secondary, index), id))
                 })
-                .collect::<Vec<(RectScore, SpriteData)>>();
+                .min();
 
-            // If the placeable list is empty at this point, we can break out and
+            // If there is no best score at this point, we can break out and
             // return all SpriteDatas we were not able to place
-            if placeable.is_empty() {
-                break;
-            }
-
-            placeable.sort_by_key(|(score, _)| score.primary);
-            let (score, sprite) = {
-                // Find out if there's multiple with the best score
-                let best_scored = placeable
-                    .iter()
-                    .filter(|(score, _)| score.primary == placeable[0].0.primary)
-                    .collect::<Vec<&(RectScore, SpriteData)>>();
-
-                // If not, we have found the next best fit! Othweise, take the
-                // next best by the secondary score
-                if best_scored.len() == 1 {
-                    best_scored[0]
-                } else {
-                    best_scored
-                        .iter()
-                        .min_by_key(|(score, _)| score.secondary)
-                        .expect("Unreachable")
-                }
+            let (_, id) = match best {
+                Some(best) => best,
+                None => break,
             };
 
-            self.place_rect(score.placement, sprite.id);
-            sprites.retain(|s| s.id != sprite.id);
-            placed.push(sprite.id);
+            let group = &mut groups[id];
+            let index = group.sprites.pop_front().expect("Unreachable");
+            let placement = group.scores[0].placement;
+            let (removed, added) = self.place_rect(placement, sprites[index].id);
+            let added = added
+                .into_iter()
+                .map(|id| (*self.free.get(id), id))
+                .collect::<Vec<(Rect, usize)>>();
+
+            // The kept scores stay the best ones as long as their free rects
+            // are still there, so only the new free rects have to be scored.
+            // Sizes that didn't fit before won't fit into them either.
+            #[cfg(feature = "rayon")]
+            let scores = groups.par_iter_mut();
+            #[cfg(not(feature = "rayon"))]
+            let scores = groups.iter_mut();
+            let bin = &*self;
+            scores
+                .filter(|group| !group.sprites.is_empty() && !group.scores.is_empty())
+                .for_each(|group| {
+                    group
+                        .scores
+                        .retain(|score| !removed.contains(&score.free_id));
+                    if group.scores.is_empty() {
+                        group.scores = bin.score_rect(group.dimensions);
+                        return;
+                    }
+
+                    // Free rects that weren't kept are all worse than the
+                    // last kept one, so new rects can only be kept if
+                    // they're better than that
+                    for (rect, id) in &added {
+                        let score = match score_fit(group.dimensions, rect, *id) {
+                            Some(score) => score,
+                            None => continue,
+                        };
+
+                        let last = group.scores[group.scores.len() - 1].key();
+                        if score.key() < last {
+                            let position = group
+                                .scores
+                                .iter()
+                                .position(|other| score.key() < other.key())
+                                .expect("Unreachable");
+                            group.scores.insert(position, score);
+                            group.scores.truncate(CACHED_SCORES);
+                        }
+                    }
+                });
         }
 
-        sprites
+        let mut left = groups
+            .into_iter()
+            .flat_map(|group| group.sprites)
+            .collect::<Vec<usize>>();
+        left.sort_unstable();
+        left.into_iter().map(|index| sprites[index]).collect()
     }
 
-    pub fn score_rect(&self, width: u32, height: u32) -> ScoreResult {
-        use std::cmp::{max, min};
-
-        // We score by best short side fit, since it's the best performing
-        // strategy according to the reference implementation
-        let mut best_short = std::u32::MAX;
-        let mut best_long = std::u32::MAX;
-        let mut placement = Rect::new(0, 0, 0, 0);
-        let mut fit_found = false;
+    // The best `CACHED_SCORES` scores among all free rects, best first
+    pub fn score_rect(&self, dimensions: (u32, u32)) -> Vec<RectScore> {
+        let mut scores = self
+            .free
+            .rects
+            .iter()
+            .filter_map(|(rect, id)| score_fit(dimensions, rect, *id))
+            .collect::<Vec<RectScore>>();
 
-        for rect in &self.free {
-            let other_width = (rect.max_x - rect.min_x) as i32;
-            let other_height = (rect.max_y - rect.min_y) as i32;
+        if scores.len() > CACHED_SCORES {
+            scores.select_nth_unstable_by_key(CACHED_SCORES, RectScore::key);
+            scores.truncate(CACHED_SCORES);
+        }
 
-            let leftover_horiz = (other_width - width as i32).abs() as u32;
-            let leftover_vert = (other_height - height as i32).abs() as u32;
+        scores.sort_unstable_by_key(RectScore::key);
+        scores
+    }
 
-            let short_side_fit = min(leftover_horiz, leftover_vert);
-            let long_side_fit = max(leftover_horiz, leftover_vert);
+    // Whether the rect lies within the bin without overlapping any sprites
+    fn is_free(&self, rect: &Rect) -> bool {
+        self.free.contains(rect)
+    }
 
-            if short_side_fit < best_short
-                || (short_side_fit == best_short && long_side_fit < best_long)
-            {
-                best_short = short_side_fit;
-                best_long = long_side_fit;
-                placement = Rect::xywh(rect.min_x, rect.min_y, width, height);
-                fit_found = true;
-            }
-        }
+    // Returns the ids of the free rects that were removed and added
+    fn place_rect(&mut self, rect: Rect, sprite_id: usize) -> (Vec<usize>, Vec<usize>) {
+        let removed = self
+            .free
+            .near(&rect)
+            .into_iter()
+            .filter(|id| !self.free.get(*id).no_intersection(&rect))
+            .collect::<Vec<usize>>();
 
-        if !fit_found {
-            ScoreResult::NoFit
-        } else {
-            ScoreResult::FitFound(RectScore {
-                placement,
-                primary: best_short as i32,
-                secondary: best_long as i32,
-            })
+        let mut pieces = Vec::new();
+        for id in &removed {
+            let split = self.free.remove(*id);
+            split_rect(&mut pieces, split, rect);
         }
-    }
 
-    fn place_rect(&mut self, rect: Rect, sprite_id: usize) {
-        let mut to_process = self.free.len();
-        let mut i = 0;
+        // Remaining free rects can't be contained in the new ones, since
+        // they weren't contained in the rects that were split either
+        remove_redundant_rects(&mut pieces);
+        pieces.retain(|piece| !self.free.contains(piece));
+        let added = pieces
+            .into_iter()
+            .map(|piece| self.free.insert(piece))
+            .collect();
 
-        while i < to_process {
-            if self.free[i].no_intersection(&rect) {
-                i += 1;
-                continue;
-            }
+        self.used.push((rect, sprite_id));
+        (removed, added)
+    }
+}
 
-            let to_split = self.free.swap_remove(i);
-            self.split_rect(to_split, rect);
-            to_process -= 1;
+/// The largest empty rectangles left between the sprites on a sheet, the
+/// same ones the maxrects packer would try to place further sprites into.
+/// Sprites are extended by `padding` to the right and bottom, like when
+/// packing, so the gaps between them don't count as free.
+pub(crate) fn free_rects(
+    dimensions: (u32, u32),
+    anchors: &[SpriteAnchor],
+    padding: u32,
+) -> Vec<((u32, u32), (u32, u32))> {
+    let (width, height) = dimensions;
+    let mut bin = MaxRectsBin::new(width, height);
+    for anchor in anchors {
+        let (x, y) = anchor.position;
+        let right = min(width, x + anchor.dimensions.0 + padding);
+        let bottom = min(height, y + anchor.dimensions.1 + padding);
+        if anchor.dimensions.0 > 0 && anchor.dimensions.1 > 0 && right > x && bottom > y {
+            bin.place_rect(Rect::xywh(x, y, right - x, bottom - y), anchor.id);
         }
+    }
 
-        remove_redundant_rects(&mut self.free);
-        self.used.push((rect, sprite_id));
+    let mut rects = bin
+        .free
+        .rects
+        .iter()
+        .map(|(rect, _)| {
+            (
+                (rect.min_x, rect.min_y),
+                (rect.max_x - rect.min_x, rect.max_y - rect.min_y),
+            )
+        })
+        .collect::<Vec<_>>();
+    rects.sort();
+    rects
+}
+
+// We score by best short side fit, since it's the best performing
+// strategy according to the reference implementation
+fn score_fit(dimensions: (u32, u32), rect: &Rect, free_id: usize) -> Option<RectScore> {
+    let (width, height) = dimensions;
+    if width > rect.max_x - rect.min_x || height > rect.max_y - rect.min_y {
+        return None;
     }
 
-    fn split_rect(&mut self, split: Rect, place: Rect) {
-        if place.min_x < split.max_x && place.max_x > split.min_x {
-            // New node at the top side of the placed node.
-            if place.min_y > split.min_y && place.min_y < split.max_y {
-                let height = split.max_y - split.min_y;
-                let new_min_y = place.min_y - split.min_y;
+    let leftover_horiz = rect.max_x - rect.min_x - width;
+    let leftover_vert = rect.max_y - rect.min_y - height;
 
-                self.free.push(Rect {
-                    min_y: new_min_y,
-                    max_y: new_min_y + height,
-                    ..split
-                })
-            }
+    Some(RectScore {
+        placement: Rect::xywh(rect.min_x, rect.min_y, width, height),
+        free_id,
+        primary: min(leftover_horiz, leftover_vert),
+        secondary: max(leftover_horiz, leftover_vert),
+    })
+}
 
-            if place.max_y < split.max_y {
-                let new_min_y = place.max_y;
-                let height = split.max_y - place.max_y;
+fn split_rect(free: &mut Vec<Rect>, split: Rect, place: Rect) {
+    if place.min_x < split.max_x && place.max_x > split.min_x {
+        // New node at the top side of the placed node.
+        if place.min_y > split.min_y && place.min_y < split.max_y {
+            free.push(Rect {
+                max_y: place.min_y,
+                ..split
+            })
+        }
 
-                self.free.push(Rect {
-                    min_y: new_min_y,
-                    max_y: new_min_y + height,
-                    ..split
-                });
-            }
+        if place.max_y < split.max_y {
+            let new_min_y = place.max_y;
+            let height = split.max_y - place.max_y;
+
+            free.push(Rect {
+                min_y: new_min_y,
+                max_y: new_min_y + height,
+                ..split
+            });
         }
+    }
 
-        if place.min_y < split.max_y && place.max_y > split.min_y {
-            // New node at the left side of the placed node.
-            if place.min_x > split.min_x && place.min_x < split.max_x {
-                let width = place.min_x - split.min_x;
+    if place.min_y < split.max_y && place.max_y > split.min_y {
+        // New node at the left side of the placed node.
+        if place.min_x > split.min_x && place.min_x < split.max_x {
+            let width = place.min_x - split.min_x;
 
-                self.free.push(Rect {
-                    max_x: split.min_x + width,
-                    ..split
-                });
-            }
+            free.push(Rect {
+                max_x: split.min_x + width,
+                ..split
+            });
+        }
 
-            // New node at the right side of the placed node.
-            if place.max_x < split.max_x {
-                let new_min_x = place.max_x;
-                let width = split.max_x - place.max_x;
+        // New node at the right side of the placed node.
+        if place.max_x < split.max_x {
+            let new_min_x = place.max_x;
+            let width = split.max_x - place.max_x;
 
-                self.free.push(Rect {
-                    min_x: new_min_x,
-                    max_x: new_min_x + width,
-                    ..split
-                });
-            }
+            free.push(Rect {
+                min_x: new_min_x,
+                max_x: new_min_x + width,
+                ..split
+            });
         }
     }
 }
 
+// Takes the padding that was added to the right and bottom of every sprite
+// off again, and shrinks the sheet to fit the sprites without it
+pub(super) fn remove_padding(result: PackerResult, padding: u32) -> PackerResult {
+    let anchors = result
+        .anchors
+        .into_iter()
+        .map(|anchor| {
+            let (width, height) = anchor.dimensions;
+            SpriteAnchor {
+                dimensions: (width - padding, height - padding),
+                ..anchor
+            }
+        })
+        .collect::<Vec<SpriteAnchor>>();
+
+    let width = anchors
+        .iter()
+        .map(|a| a.position.0 + a.dimensions.0)
+        .max()
+        .unwrap_or(0);
+
+    let height = anchors
+        .iter()
+        .map(|a| a.position.1 + a.dimensions.1)
+        .max()
+        .unwrap_or(0);
+
+    PackerResult {
+        dimensions: (width, height),
+        anchors,
+    }
+}
+
 fn remove_redundant_rects(rects: &mut Vec<Rect>) {
     let mut i = 0;
     while let Some(next) = rects.get(i).cloned() {
@@ -377,6 +814,7 @@ fn remove_redundant_rects(rects: &mut Vec<Rect>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pack::validate::validate_padded;
 
     #[test]
     fn remove_redundant() {
@@ -407,7 +845,7 @@ mod tests {
             .max_height(10 * 10);
 
         let result = MaxrectsPacker::pack(&sprites, options);
-        let first = result.iter().next().expect("should have 1 result");
+        let first = result.first().expect("should have 1 result");
 
         assert_eq!(result.len(), 1);
 
@@ -419,7 +857,7 @@ mod tests {
         // The new sprite will bu pushed to the next line
         sprites.push(SpriteData::new(11, (10, 20)));
         let result = MaxrectsPacker::pack(&sprites, options);
-        let first = result.iter().next().expect("should have 1 result");
+        let first = result.first().expect("should have 1 result");
 
         assert_eq!(first.dimensions.0, 30);
         assert_eq!(first.dimensions.1, 100);
@@ -445,9 +883,7 @@ mod tests {
             .map(|i| SpriteData::new(i, (100, 100)))
             .collect::<Vec<SpriteData>>();
 
-        let options = MaxrectsOptions::default()
-            .max_width(50)
-            .max_height(50);
+        let options = MaxrectsOptions::default().max_width(50).max_height(50);
 
         let result = MaxrectsPacker::pack(&oversized, options);
 
@@ -457,4 +893,289 @@ mod tests {
             assert_eq!(bin.dimensions.1, 100);
         }
     }
+
+    #[test]
+    fn pack_oversized_ids() {
+        let sprites = vec![SpriteData::new(3, (10, 10)), SpriteData::new(7, (100, 10))];
+
+        let options = MaxrectsOptions::default().max_width(50).max_height(50);
+        let result = MaxrectsPacker::pack(&sprites, options);
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].anchors[0].id, 3);
+        assert_eq!(result[1].anchors[0].id, 7);
+        assert_eq!(result[1].dimensions, (100, 10));
+    }
+
+    #[test]
+    fn pack_oversized_policies() {
+        let sprites = vec![SpriteData::new(0, (10, 10)), SpriteData::new(1, (40, 10))];
+        let options = MaxrectsOptions::default().max_width(20).max_height(20);
+
+        let result = MaxrectsPacker::try_pack(&sprites, options.oversized(OversizedPolicy::Error));
+        assert_eq!(
+            result.unwrap_err(),
+            PackError::Oversized {
+                id: 1,
+                dimensions: (40, 10)
+            }
+        );
+
+        let options = options.oversized(OversizedPolicy::Downscale);
+        let result = MaxrectsPacker::try_pack(&sprites, options).unwrap();
+        assert_eq!(result.len(), 1);
+
+        let anchor = result[0]
+            .anchors
+            .iter()
+            .find(|anchor| anchor.id == 1)
+            .unwrap();
+        assert_eq!(anchor.dimensions, (20, 5));
+        assert_eq!(validate_padded(&result, &sprites, 0), Ok(()));
+        assert_eq!(fit_dimensions((5, 40), (20, 20)), Some((2, 20)));
+        assert_eq!(fit_dimensions((5, 40), (0, 20)), None);
+    }
+
+    #[test]
+    fn pack_max_pages() {
+        let sprites = (0..8)
+            .map(|i| SpriteData::new(i, (10, 10)))
+            .collect::<Vec<SpriteData>>();
+        let options = MaxrectsOptions::default().max_width(20).max_height(20);
+
+        assert!(MaxrectsPacker::try_pack(&sprites, options.max_pages(2)).is_ok());
+        assert_eq!(
+            MaxrectsPacker::try_pack(&sprites, options.max_pages(1)).unwrap_err(),
+            PackError::TooManyPages {
+                pages: 2,
+                max_pages: 1
+            }
+        );
+    }
+
+    #[test]
+    fn free_rects_grid() {
+        let mut free = FreeRects::new(1000, 1000);
+        let left = free.insert(Rect::xywh(0, 0, 100, 1000));
+        let corner = free.insert(Rect::xywh(900, 900, 100, 100));
+
+        assert_eq!(free.near(&Rect::xywh(50, 500, 10, 10)), vec![left]);
+        assert_eq!(free.near(&Rect::xywh(950, 990, 50, 10)), vec![corner]);
+        assert!(free.near(&Rect::xywh(500, 500, 10, 10)).is_empty());
+        assert!(free.contains(&Rect::xywh(910, 910, 90, 90)));
+
+        free.remove(left);
+        assert_eq!(*free.get(corner), Rect::xywh(900, 900, 100, 100));
+        assert_eq!(free.near(&Rect::xywh(0, 0, 1000, 1000)), vec![corner]);
+        assert!(!free.contains(&Rect::xywh(0, 0, 10, 10)));
+    }
+
+    #[test]
+    fn pack_padded_unbounded() {
+        let sprites = vec![SpriteData::new(0, (10, 10)), SpriteData::new(1, (10, 10))];
+
+        let options = MaxrectsOptions::default()
+            .max_width(u32::MAX)
+            .max_height(u32::MAX)
+            .padding(2);
+        let result = MaxrectsPacker::pack(&sprites, options);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].anchors.len(), 2);
+    }
+
+    #[test]
+    fn pack_padded() {
+        let sprites = (0..4)
+            .map(|i| SpriteData::new(i, (10, 10)))
+            .collect::<Vec<SpriteData>>();
+
+        let options = MaxrectsOptions::default()
+            .max_width(22)
+            .max_height(22)
+            .padding(2);
+
+        let result = MaxrectsPacker::pack(&sprites, options);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].dimensions, (22, 22));
+
+        let mut positions = result[0]
+            .anchors
+            .iter()
+            .map(|anchor| {
+                assert_eq!(anchor.dimensions, (10, 10));
+                anchor.position
+            })
+            .collect::<Vec<(u32, u32)>>();
+        positions.sort();
+
+        assert_eq!(positions, vec![(0, 0), (0, 12), (12, 0), (12, 12)]);
+    }
+
+    #[test]
+    fn split_free_rects() {
+        let mut bin = MaxRectsBin::new(30, 30);
+        bin.place_rect(Rect::xywh(10, 10, 10, 10), 0);
+
+        assert!(bin.is_free(&Rect::xywh(0, 0, 30, 10)));
+        assert!(bin.is_free(&Rect::xywh(0, 0, 10, 30)));
+        assert!(bin.is_free(&Rect::xywh(0, 20, 30, 10)));
+        assert!(bin.is_free(&Rect::xywh(20, 0, 10, 30)));
+        assert!(!bin.is_free(&Rect::xywh(5, 5, 10, 10)));
+    }
+
+    #[test]
+    fn free_rects_between_sprites() {
+        let anchors = vec![
+            SpriteAnchor::new(0, (0, 0), (10, 10)),
+            SpriteAnchor::new(1, (0, 11), (10, 9)),
+        ];
+
+        let rects = free_rects((20, 20), &anchors, 0);
+        assert_eq!(rects, vec![((0, 10), (20, 1)), ((10, 0), (10, 20))]);
+
+        let rects = free_rects((20, 20), &anchors, 1);
+        assert_eq!(rects, vec![((11, 0), (9, 20))]);
+    }
+
+    #[test]
+    fn pack_stable() {
+        let sprites = (0..4)
+            .map(|i| SpriteData::new(i, (10, 10)))
+            .collect::<Vec<SpriteData>>();
+
+        // Sprite 3 takes the spot of sprite 0, and sprite 1 was resized
+        let previous = vec![
+            SpriteAnchor::new(0, (20, 20), (10, 10)),
+            SpriteAnchor::new(1, (0, 20), (5, 5)),
+            SpriteAnchor::new(2, (10, 0), (10, 10)),
+            SpriteAnchor::new(3, (20, 20), (10, 10)),
+        ];
+        let options = MaxrectsOptions::default().max_width(30).max_height(30);
+        let options = StableMaxrectsOptions::new(options, previous);
+
+        let result = StableMaxrectsPacker::pack(&sprites, options);
+        assert_eq!(result.len(), 1);
+
+        let position = |id| {
+            result[0]
+                .anchors
+                .iter()
+                .find(|anchor| anchor.id == id)
+                .map(|anchor| anchor.position)
+                .expect("sprite should have been packed")
+        };
+
+        assert_eq!(position(0), (20, 20));
+        assert_eq!(position(2), (10, 0));
+        assert_ne!(position(3), (20, 20));
+        assert_eq!(result[0].anchors.len(), 4);
+    }
+
+    #[test]
+    fn pack_stable_outside_of_bin() {
+        let sprites = vec![SpriteData::new(0, (10, 10)), SpriteData::new(1, (10, 10))];
+
+        // Neither of these fit into the bin, and the first doesn't even
+        // fit into a u32
+        let previous = vec![
+            SpriteAnchor::new(0, (u32::MAX - 5, 0), (10, 10)),
+            SpriteAnchor::new(1, (25, 0), (10, 10)),
+        ];
+        let options = MaxrectsOptions::default().max_width(30).max_height(30);
+        let options = StableMaxrectsOptions::new(options, previous);
+
+        let result = StableMaxrectsPacker::pack(&sprites, options);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].anchors.len(), 2);
+        for anchor in &result[0].anchors {
+            assert!(anchor.position.0 + anchor.dimensions.0 <= 30);
+        }
+    }
+
+    #[test]
+    fn pack_without_overlaps() {
+        let sprites = (0..50)
+            .map(|i| SpriteData::new(i, (5 + (i as u32 * 7) % 23, 5 + (i as u32 * 13) % 19)))
+            .collect::<Vec<SpriteData>>();
+
+        let options = MaxrectsOptions::default().max_width(64).max_height(64);
+        let result = MaxrectsPacker::pack(&sprites, options);
+
+        for bin in &result {
+            assert!(bin.dimensions.0 <= 64 && bin.dimensions.1 <= 64);
+
+            let rects = bin
+                .anchors
+                .iter()
+                .map(|a| Rect::xywh(a.position.0, a.position.1, a.dimensions.0, a.dimensions.1))
+                .collect::<Vec<Rect>>();
+            for (i, rect) in rects.iter().enumerate() {
+                assert!(rects[i + 1..]
+                    .iter()
+                    .all(|other| rect.no_intersection(other)));
+            }
+        }
+
+        let packed = result.iter().map(|bin| bin.anchors.len()).sum::<usize>();
+        assert_eq!(packed, sprites.len());
+    }
+
+    proptest! {
+        #[test]
+        fn no_overlaps(
+            sizes in proptest::collection::vec((1u32..40, 1u32..40), 0..80),
+            max_width in 40u32..120,
+            max_height in 40u32..120,
+            padding in 0u32..3,
+        ) {
+            let sprites = sizes
+                .iter()
+                .enumerate()
+                .map(|(id, size)| SpriteData::new(id, *size))
+                .collect::<Vec<SpriteData>>();
+
+            let options = MaxrectsOptions::default()
+                .max_width(max_width)
+                .max_height(max_height)
+                .padding(padding);
+            let result = MaxrectsPacker::pack(&sprites, options);
+
+            for bin in &result {
+                prop_assert!(bin.dimensions.0 <= max_width && bin.dimensions.1 <= max_height);
+            }
+            prop_assert_eq!(validate_padded(&result, &sprites, padding), Ok(()));
+        }
+
+        // The previous anchors don't have to come from a valid sheet, they
+        // may overlap, lie outside of the sheet or have other dimensions
+        #[test]
+        fn stable_no_overlaps(
+            sizes in proptest::collection::vec((1u32..40, 1u32..40), 0..80),
+            previous in proptest::collection::vec(
+                (0usize..80, (0u32..100, 0u32..100), (1u32..40, 1u32..40)),
+                0..80,
+            ),
+            padding in 0u32..3,
+        ) {
+            let sprites = sizes
+                .iter()
+                .enumerate()
+                .map(|(id, size)| SpriteData::new(id, *size))
+                .collect::<Vec<SpriteData>>();
+            let previous = previous
+                .into_iter()
+                .map(|(id, position, size)| SpriteAnchor::new(id, position, size))
+                .collect::<Vec<SpriteAnchor>>();
+
+            let options = MaxrectsOptions::default()
+                .max_width(64)
+                .max_height(64)
+                .padding(padding);
+            let options = StableMaxrectsOptions::new(options, previous);
+            let result = StableMaxrectsPacker::pack(&sprites, options);
+
+            prop_assert_eq!(validate_padded(&result, &sprites, padding), Ok(()));
+        }
+    }
 }
@@ -1,6 +1,9 @@
 pub mod maxrects;
 pub mod simple;
+pub mod validate;
 
+use std::error::Error;
+use std::fmt;
 use {SpriteAnchor, SpriteData};
 
 #[derive(Debug, Clone)]
@@ -13,4 +16,42 @@ pub trait Packer {
     type Options;
 
     fn pack(sprites: &[SpriteData], options: Self::Options) -> Vec<PackerResult>;
+
+    /// Like `pack`, but fails instead of panicking if the sprites can't be
+    /// packed within the limits set in the options. Packers without such
+    /// limits don't need to implement this.
+    fn try_pack(
+        sprites: &[SpriteData],
+        options: Self::Options,
+    ) -> Result<Vec<PackerResult>, PackError> {
+        Ok(Self::pack(sprites, options))
+    }
+}
+
+/// Why a packer couldn't place the sprites within its limits.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PackError {
+    /// The sprite is larger than the maximum sheet size.
+    Oversized { id: usize, dimensions: (u32, u32) },
+    /// The sprites need more sheets than allowed.
+    TooManyPages { pages: usize, max_pages: usize },
 }
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PackError::Oversized { id, dimensions } => write!(
+                f,
+                "Sprite {} is {}x{}, which is larger than the maximum sheet size",
+                id, dimensions.0, dimensions.1
+            ),
+            PackError::TooManyPages { pages, max_pages } => write!(
+                f,
+                "The sprites need {} sheets, but at most {} are allowed",
+                pages, max_pages
+            ),
+        }
+    }
+}
+
+impl Error for PackError {}
@@ -1,107 +1,233 @@
-use std::cmp::{min, Ordering};
-use {Packer, PackerResult, SpriteAnchor, SpriteData};
+use super::maxrects::remove_padding;
+use std::cmp::{max, min, Reverse};
+use {PackError, Packer, PackerResult, SpriteAnchor, SpriteData};
 
+/// Places sprites next to each other in rows. This is a lot quicker than
+/// maxrects, but leaves more space unused unless the sprites have similar
+/// heights.
 pub struct SimplePacker;
 
-impl Packer for SimplePacker {
-    type Options = ();
-
-    fn pack(sprites: &[SpriteData], _options: ()) -> Vec<PackerResult> {
-        let mut sprites = sprites.iter().cloned().collect::<Vec<SpriteData>>();
-
-        let mut free = Vec::new();
-        let mut absolute = Vec::new();
-
-        sprites.sort_by(compare_area);
-        sprites.reverse();
-        free.push((0, 0));
-
-        for sprite in sprites.iter() {
-            // Push the sprite to the next free anchor
-            let next_free = *free.first().expect("No free anchor");
-            absolute.push(SpriteAnchor::new(sprite.id, next_free, sprite.dimensions));
-
-            // find new anchors
-            let mut new_right = (next_free.0 + sprite.dimensions.0, next_free.1);
-            let mut new_bottom = (next_free.0, next_free.1 + sprite.dimensions.1);
-
-            // still finding new anchors
-            for i in 1..(free.len() - 1) {
-                // If we removed an anchor after the first round,
-                // we might be out of bounds at this point
-                if i > 1 && i >= free.len() {
-                    break;
-                }
-
-                if free[i].0 >= free[0].0 && free[i].0 <= new_right.0 {
-                    new_right.1 = min(new_right.1, free[i].1);
-                    free.remove(i);
-                    continue;
-                }
-
-                if free[i].1 >= free[0].1 && free[i].1 <= new_bottom.1 {
-                    new_bottom.0 = min(new_bottom.0, free[i].0);
-                    free.remove(i);
-                    continue;
-                }
-            }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimpleStrategy {
+    /// Sprites are placed in input order, starting a new row whenever the
+    /// current one is full, so e.g. the frames of an animation stay in order.
+    Rows,
+    /// Sprites are sorted by height and placed on the first row they fit
+    /// on, which fills up the gaps at the end of earlier rows.
+    Shelves,
+}
 
-            // remove first, push new anchors
-            free.remove(0);
+#[derive(Debug, Clone, Copy)]
+pub struct SimpleOptions {
+    max_width: u32,
+    max_height: u32,
+    padding: u32,
+    strategy: SimpleStrategy,
+}
 
-            if !free.contains(&new_right) {
-                free.push(new_right);
-            }
+impl Default for SimpleOptions {
+    fn default() -> Self {
+        SimpleOptions {
+            max_width: u32::MAX,
+            max_height: u32::MAX,
+            padding: 0,
+            strategy: SimpleStrategy::Shelves,
+        }
+    }
+}
 
-            if !free.contains(&new_bottom) {
-                free.push(new_bottom);
-            }
+impl SimpleOptions {
+    /// Rows are about as wide as the sheet ends up high, but never wider
+    /// than this.
+    pub fn max_width(mut self, width: u32) -> Self {
+        self.max_width = width;
+        self
+    }
+
+    /// Sprites that don't fit into a sheet anymore are placed on another
+    /// one. Without a max height, all sprites end up on a single sheet.
+    ///
+    /// Sprites that are larger than the max size make packing fail with
+    /// `PackError::Oversized`. `pack` panics in that case, use `try_pack`
+    /// to handle it.
+    pub fn max_height(mut self, height: u32) -> Self {
+        self.max_height = height;
+        self
+    }
+
+    /// Transparent pixels to leave between sprites. There is no padding
+    /// along the edges of the sheet.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
 
-            free.sort_by(compare_pos);
+    pub fn strategy(mut self, strategy: SimpleStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+}
+
+impl Packer for SimplePacker {
+    type Options = SimpleOptions;
+
+    fn pack(sprites: &[SpriteData], options: SimpleOptions) -> Vec<PackerResult> {
+        Self::try_pack(sprites, options).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_pack(
+        sprites: &[SpriteData],
+        options: SimpleOptions,
+    ) -> Result<Vec<PackerResult>, PackError> {
+        if let Some(sprite) = sprites.iter().find(|sprite| {
+            let (width, height) = sprite.dimensions;
+            width > options.max_width || height > options.max_height
+        }) {
+            return Err(PackError::Oversized {
+                id: sprite.id,
+                dimensions: sprite.dimensions,
+            });
         }
 
-        let width = free
+        // Like for maxrects, padding is added to the right and bottom of
+        // every sprite, and the bins grow by the same amount
+        let padding = options.padding;
+        let max_width = options.max_width.saturating_add(padding);
+        let max_height = options.max_height.saturating_add(padding);
+
+        let mut sprites = sprites
             .iter()
-            .max_by(|a, b| a.0.cmp(&b.0))
-            .expect("Invalid: No free anchors")
-            .0;
+            .map(|sprite| {
+                let (width, height) = sprite.dimensions;
+                SpriteData::new(sprite.id, (width + padding, height + padding))
+            })
+            .collect::<Vec<SpriteData>>();
 
-        let height = free
+        if options.strategy == SimpleStrategy::Shelves {
+            sprites
+                .sort_by_key(|sprite| (Reverse(sprite.dimensions.1), Reverse(sprite.dimensions.0)));
+        }
+
+        let area = sprites
             .iter()
-            .max_by(|a, b| a.1.cmp(&b.1))
-            .expect("Invalid: No free anchors")
-            .1;
+            .map(|sprite| u64::from(sprite.dimensions.0) * u64::from(sprite.dimensions.1))
+            .sum::<u64>();
+        let widest = sprites
+            .iter()
+            .map(|sprite| sprite.dimensions.0)
+            .max()
+            .unwrap_or(0);
+        let square = (area as f64).sqrt().ceil() as u64;
+        let width = min(u64::from(max_width), max(u64::from(widest), square)) as u32;
 
-        // Finally sort the anchors so that they are in the same order as the
-        // input sprites
-        absolute.sort_by_key(|s| s.id);
+        let mut bins = Vec::<Bin>::new();
+        for sprite in sprites {
+            let placed = match options.strategy {
+                SimpleStrategy::Rows => bins
+                    .last_mut()
+                    .is_some_and(|bin| bin.insert(sprite, width, max_height, false)),
+                SimpleStrategy::Shelves => bins
+                    .iter_mut()
+                    .any(|bin| bin.insert(sprite, width, max_height, true)),
+            };
 
-        let result = PackerResult {
-            dimensions: (width, height),
-            anchors: absolute,
-        };
+            if !placed {
+                let mut bin = Bin::default();
+                bin.insert(sprite, width, max_height, false);
+                bins.push(bin);
+            }
+        }
 
-        vec![result]
+        Ok(bins
+            .into_iter()
+            .map(|bin| remove_padding(bin.into_result(), padding))
+            .collect())
     }
 }
 
-fn compare_area(a: &SpriteData, b: &SpriteData) -> Ordering {
-    (a.dimensions.0 * a.dimensions.1).cmp(&(b.dimensions.0 * b.dimensions.1))
+// A row of sprites, `width` is the space already taken up
+#[derive(Debug, Clone)]
+struct Shelf {
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Bin {
+    shelves: Vec<Shelf>,
+    anchors: Vec<SpriteAnchor>,
 }
 
-fn compare_pos(a: &(u32, u32), b: &(u32, u32)) -> Ordering {
-    // NOTE(happenslol): We might overflow here quickly if the output
-    // sprite becomes too big, so we use u64s. This is why this algorithm
-    // doesn't scale very well...
-    let a = (a.0 as u64, a.1 as u64);
-    let b = (b.0 as u64, b.1 as u64);
+impl Bin {
+    // Places the sprite on the last shelf, or on the first one it fits on if
+    // `first_fit` is set, and opens a new shelf below the others otherwise.
+    // The last shelf can grow to fit taller sprites.
+    fn insert(&mut self, sprite: SpriteData, width: u32, max_height: u32, first_fit: bool) -> bool {
+        let (sprite_width, sprite_height) = sprite.dimensions;
+        let last = self.shelves.len().saturating_sub(1);
+        let first = if first_fit { 0 } else { last };
+
+        for (i, shelf) in self.shelves.iter_mut().enumerate().skip(first) {
+            let fits_height = sprite_height <= shelf.height
+                || (i == last && sprite_height <= max_height - shelf.y);
+
+            if fits_height && sprite_width <= width - shelf.width {
+                let position = (shelf.width, shelf.y);
+                self.anchors
+                    .push(SpriteAnchor::new(sprite.id, position, sprite.dimensions));
+                shelf.width += sprite_width;
+                shelf.height = max(shelf.height, sprite_height);
+                return true;
+            }
+        }
 
-    (a.0.pow(4) + a.1.pow(4)).cmp(&(b.0.pow(4) + b.1.pow(4)))
+        let y = self
+            .shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0);
+        if sprite_height > max_height - y {
+            return false;
+        }
+
+        self.anchors
+            .push(SpriteAnchor::new(sprite.id, (0, y), sprite.dimensions));
+        self.shelves.push(Shelf {
+            y,
+            width: sprite_width,
+            height: sprite_height,
+        });
+        true
+    }
+
+    fn into_result(self) -> PackerResult {
+        let mut anchors = self.anchors;
+        anchors.sort_by_key(|anchor| anchor.id);
+
+        let width = self
+            .shelves
+            .iter()
+            .map(|shelf| shelf.width)
+            .max()
+            .unwrap_or(0);
+        let height = self
+            .shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0);
+
+        PackerResult {
+            dimensions: (width, height),
+            anchors,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pack::validate::validate_padded;
 
     #[test]
     fn pack_square() {
@@ -109,9 +235,91 @@ mod tests {
             .map(|i| SpriteData::new(i, (20, 20)))
             .collect::<Vec<SpriteData>>();
 
-        let result = SimplePacker::pack(&sprites, ());
+        let result = SimplePacker::pack(&sprites, SimpleOptions::default());
 
         assert_eq!(result[0].dimensions.0, 20 * 4);
         assert_eq!(result[0].dimensions.1, 20 * 4);
     }
+
+    #[test]
+    fn pack_rows_in_order() {
+        let sprites = vec![
+            SpriteData::new(0, (10, 5)),
+            SpriteData::new(1, (10, 20)),
+            SpriteData::new(2, (10, 10)),
+            SpriteData::new(3, (10, 20)),
+        ];
+
+        let options = SimpleOptions::default()
+            .max_width(20)
+            .strategy(SimpleStrategy::Rows);
+        let result = SimplePacker::pack(&sprites, options);
+
+        let positions = result[0]
+            .anchors
+            .iter()
+            .map(|anchor| anchor.position)
+            .collect::<Vec<(u32, u32)>>();
+        assert_eq!(positions, vec![(0, 0), (10, 0), (0, 20), (10, 20)]);
+        assert_eq!(result[0].dimensions, (20, 40));
+    }
+
+    #[test]
+    fn pack_multiple() {
+        let sprites = (0..30)
+            .map(|i| SpriteData::new(i, (10, 10)))
+            .collect::<Vec<SpriteData>>();
+
+        let options = SimpleOptions::default().max_width(50).max_height(20);
+        let result = SimplePacker::pack(&sprites, options);
+
+        assert_eq!(result.len(), 3);
+        assert!(result.iter().all(|bin| bin.dimensions == (50, 20)));
+    }
+
+    #[test]
+    fn pack_oversized() {
+        let sprites = vec![SpriteData::new(0, (10, 10)), SpriteData::new(1, (30, 5))];
+
+        let options = SimpleOptions::default().max_width(20).max_height(20);
+        let result = SimplePacker::try_pack(&sprites, options);
+
+        assert_eq!(
+            result.unwrap_err(),
+            PackError::Oversized {
+                id: 1,
+                dimensions: (30, 5)
+            }
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn no_overlaps(
+            sizes in proptest::collection::vec((1u32..40, 1u32..40), 0..80),
+            max_width in 40u32..120,
+            max_height in 40u32..120,
+            padding in 0u32..3,
+            rows in proptest::bool::ANY,
+        ) {
+            let sprites = sizes
+                .iter()
+                .enumerate()
+                .map(|(id, size)| SpriteData::new(id, *size))
+                .collect::<Vec<SpriteData>>();
+
+            let strategy = if rows { SimpleStrategy::Rows } else { SimpleStrategy::Shelves };
+            let options = SimpleOptions::default()
+                .max_width(max_width)
+                .max_height(max_height)
+                .padding(padding)
+                .strategy(strategy);
+            let result = SimplePacker::pack(&sprites, options);
+
+            for bin in &result {
+                prop_assert!(bin.dimensions.0 <= max_width && bin.dimensions.1 <= max_height);
+            }
+            prop_assert_eq!(validate_padded(&result, &sprites, padding), Ok(()));
+        }
+    }
 }
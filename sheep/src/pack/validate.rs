@@ -0,0 +1,326 @@
+use std::collections::hash_map::HashMap;
+use std::error::Error;
+use std::fmt;
+use {PackerResult, SpriteAnchor, SpriteData};
+
+/// The first problem `validate` found in the output of a packer. `sheet`
+/// is the index of the result the anchor belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// The sprite wasn't placed on any sheet.
+    Missing { id: usize },
+    /// The sprite was placed more than once.
+    Duplicate { id: usize },
+    /// An anchor refers to a sprite that wasn't part of the input.
+    Unknown { sheet: usize, id: usize },
+    /// The anchor doesn't have the dimensions of its sprite.
+    Dimensions {
+        sheet: usize,
+        id: usize,
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// The anchor reaches past the right or bottom edge of its sheet.
+    OutOfBounds { sheet: usize, id: usize },
+    /// The two sprites overlap, or are closer together than the padding.
+    Overlap {
+        sheet: usize,
+        first: usize,
+        second: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::Missing { id } => write!(f, "Sprite {} wasn't packed", id),
+            ValidationError::Duplicate { id } => write!(f, "Sprite {} was packed twice", id),
+            ValidationError::Unknown { sheet, id } => {
+                write!(f, "Sheet {} contains unknown sprite {}", sheet, id)
+            }
+            ValidationError::Dimensions {
+                sheet,
+                id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Sprite {} on sheet {} is {}x{} instead of {}x{}",
+                id, sheet, actual.0, actual.1, expected.0, expected.1
+            ),
+            ValidationError::OutOfBounds { sheet, id } => {
+                write!(f, "Sprite {} lies outside of sheet {}", id, sheet)
+            }
+            ValidationError::Overlap {
+                sheet,
+                first,
+                second,
+            } => write!(
+                f,
+                "Sprites {} and {} overlap on sheet {}",
+                first, second, sheet
+            ),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Checks the output of a packer for the sprites it was given: every
+/// sprite is placed exactly once, with its own dimensions, inside of its
+/// sheet and without overlapping any other sprite. Useful for testing
+/// custom packers. Sprites may also be scaled down, as long as they keep
+/// their aspect ratio, like `OversizedPolicy::Downscale` does.
+pub fn validate(results: &[PackerResult], sprites: &[SpriteData]) -> Result<(), ValidationError> {
+    validate_padded(results, sprites, 0)
+}
+
+/// Like `validate`, but also checks that there are at least `padding`
+/// pixels between any two sprites, as packers that support padding leave
+/// between them.
+pub fn validate_padded(
+    results: &[PackerResult],
+    sprites: &[SpriteData],
+    padding: u32,
+) -> Result<(), ValidationError> {
+    // Maps every sprite id to its dimensions and whether it was placed yet
+    let mut placed = sprites
+        .iter()
+        .map(|sprite| (sprite.id, (sprite.dimensions, false)))
+        .collect::<HashMap<usize, ((u32, u32), bool)>>();
+
+    for (sheet, result) in results.iter().enumerate() {
+        for anchor in &result.anchors {
+            let id = anchor.id;
+            let (expected, seen) = match placed.get_mut(&id) {
+                Some(entry) => entry,
+                None => return Err(ValidationError::Unknown { sheet, id }),
+            };
+
+            if *seen {
+                return Err(ValidationError::Duplicate { id });
+            }
+            *seen = true;
+
+            if anchor.dimensions != *expected && !scaled_down(*expected, anchor.dimensions) {
+                return Err(ValidationError::Dimensions {
+                    sheet,
+                    id,
+                    expected: *expected,
+                    actual: anchor.dimensions,
+                });
+            }
+
+            let (right, bottom) = far_corner(anchor, 0);
+            if right > u64::from(result.dimensions.0) || bottom > u64::from(result.dimensions.1) {
+                return Err(ValidationError::OutOfBounds { sheet, id });
+            }
+        }
+
+        check_overlaps(sheet, &result.anchors, padding)?;
+    }
+
+    let mut missing = placed
+        .iter()
+        .filter(|(_, (_, seen))| !seen)
+        .map(|(id, _)| *id)
+        .collect::<Vec<usize>>();
+    missing.sort();
+    match missing.first() {
+        Some(id) => Err(ValidationError::Missing { id: *id }),
+        None => Ok(()),
+    }
+}
+
+// Whether `actual` is `expected` scaled down. The scaled dimensions are
+// rounded down to whole pixels, but never below one pixel, so the aspect
+// ratio can be off by up to a pixel along either axis.
+fn scaled_down(expected: (u32, u32), actual: (u32, u32)) -> bool {
+    let (width, height) = (u64::from(expected.0), u64::from(expected.1));
+    let (scaled_width, scaled_height) = (u64::from(actual.0), u64::from(actual.1));
+    if scaled_width == 0 || scaled_height == 0 || scaled_width > width || scaled_height > height {
+        return false;
+    }
+
+    let difference = (scaled_width * height).max(scaled_height * width)
+        - (scaled_width * height).min(scaled_height * width);
+    difference < width.max(height)
+}
+
+// Sweeps over the anchors from left to right, so every anchor is only
+// compared with the ones that start before its padded right edge
+fn check_overlaps(
+    sheet: usize,
+    anchors: &[SpriteAnchor],
+    padding: u32,
+) -> Result<(), ValidationError> {
+    let mut sorted = anchors.iter().collect::<Vec<&SpriteAnchor>>();
+    sorted.sort_by_key(|anchor| (anchor.position, anchor.id));
+
+    for (i, anchor) in sorted.iter().enumerate() {
+        let (right, bottom) = far_corner(anchor, padding);
+        for other in &sorted[i + 1..] {
+            if u64::from(other.position.0) >= right {
+                break;
+            }
+
+            let (_, other_bottom) = far_corner(other, padding);
+            let empty = anchor.dimensions.0 == 0
+                || anchor.dimensions.1 == 0
+                || other.dimensions.0 == 0
+                || other.dimensions.1 == 0;
+            if !empty
+                && u64::from(other.position.1) < bottom
+                && u64::from(anchor.position.1) < other_bottom
+            {
+                let (first, second) = if anchor.id < other.id {
+                    (anchor.id, other.id)
+                } else {
+                    (other.id, anchor.id)
+                };
+                return Err(ValidationError::Overlap {
+                    sheet,
+                    first,
+                    second,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn far_corner(anchor: &SpriteAnchor, padding: u32) -> (u64, u64) {
+    (
+        u64::from(anchor.position.0) + u64::from(anchor.dimensions.0) + u64::from(padding),
+        u64::from(anchor.position.1) + u64::from(anchor.dimensions.1) + u64::from(padding),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(anchors: Vec<SpriteAnchor>) -> Vec<PackerResult> {
+        vec![PackerResult {
+            dimensions: (20, 20),
+            anchors,
+        }]
+    }
+
+    #[test]
+    fn valid_sheet() {
+        let sprites = vec![SpriteData::new(0, (10, 10)), SpriteData::new(1, (10, 10))];
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (10, 10)),
+            SpriteAnchor::new(1, (10, 10), (10, 10)),
+        ]);
+
+        assert_eq!(validate(&results, &sprites), Ok(()));
+    }
+
+    #[test]
+    fn invalid_sheets() {
+        let sprites = vec![SpriteData::new(0, (10, 10)), SpriteData::new(1, (10, 10))];
+
+        let results = sheet(vec![SpriteAnchor::new(0, (0, 0), (10, 10))]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::Missing { id: 1 })
+        );
+
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (10, 10)),
+            SpriteAnchor::new(0, (10, 0), (10, 10)),
+        ]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::Duplicate { id: 0 })
+        );
+
+        let results = sheet(vec![SpriteAnchor::new(2, (0, 0), (10, 10))]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::Unknown { sheet: 0, id: 2 })
+        );
+
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (10, 10)),
+            SpriteAnchor::new(1, (10, 0), (10, 5)),
+        ]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::Dimensions {
+                sheet: 0,
+                id: 1,
+                expected: (10, 10),
+                actual: (10, 5)
+            })
+        );
+
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (10, 10)),
+            SpriteAnchor::new(1, (15, 0), (10, 10)),
+        ]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::OutOfBounds { sheet: 0, id: 1 })
+        );
+
+        let results = sheet(vec![
+            SpriteAnchor::new(1, (5, 5), (10, 10)),
+            SpriteAnchor::new(0, (0, 0), (10, 10)),
+        ]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::Overlap {
+                sheet: 0,
+                first: 0,
+                second: 1
+            })
+        );
+    }
+
+    #[test]
+    fn scaled_sprites() {
+        let sprites = vec![SpriteData::new(0, (30, 20)), SpriteData::new(1, (3, 2))];
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (15, 10)),
+            SpriteAnchor::new(1, (15, 0), (2, 1)),
+        ]);
+        assert_eq!(validate(&results, &sprites), Ok(()));
+
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (15, 5)),
+            SpriteAnchor::new(1, (15, 0), (2, 1)),
+        ]);
+        assert_eq!(
+            validate(&results, &sprites),
+            Err(ValidationError::Dimensions {
+                sheet: 0,
+                id: 0,
+                expected: (30, 20),
+                actual: (15, 5)
+            })
+        );
+    }
+
+    #[test]
+    fn padding_between_sprites() {
+        let sprites = vec![SpriteData::new(0, (5, 5)), SpriteData::new(1, (5, 5))];
+        let results = sheet(vec![
+            SpriteAnchor::new(0, (0, 0), (5, 5)),
+            SpriteAnchor::new(1, (6, 0), (5, 5)),
+        ]);
+
+        assert_eq!(validate_padded(&results, &sprites, 1), Ok(()));
+        assert_eq!(
+            validate_padded(&results, &sprites, 2),
+            Err(ValidationError::Overlap {
+                sheet: 0,
+                first: 0,
+                second: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,493 @@
+use sprite::read_sprite;
+use std::error::Error;
+use std::fmt;
+use {InputSprite, SpriteAnchor};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GridSize {
+    Cell(u32, u32),
+    Count(u32, u32),
+}
+
+/// Describes the layout of a spritesheet where all sprites are laid out in
+/// a uniform grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridOptions {
+    size: GridSize,
+    margin: u32,
+    spacing: u32,
+}
+
+impl GridOptions {
+    /// A grid with cells of the given size in pixels
+    pub fn cell_size(width: u32, height: u32) -> Self {
+        GridOptions {
+            size: GridSize::Cell(width, height),
+            margin: 0,
+            spacing: 0,
+        }
+    }
+
+    /// A grid with the given number of columns and rows, filling the
+    /// whole image
+    pub fn cell_count(columns: u32, rows: u32) -> Self {
+        GridOptions {
+            size: GridSize::Count(columns, rows),
+            margin: 0,
+            spacing: 0,
+        }
+    }
+
+    /// Space around the whole grid, in pixels
+    pub fn margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Space between two neighbouring cells, in pixels
+    pub fn spacing(mut self, spacing: u32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+}
+
+/// Why a sheet couldn't be sliced into a grid.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SliceError {
+    /// The margin, spacing and cell sizes add up to more pixels than any
+    /// sheet can have.
+    Overflow { options: GridOptions },
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SliceError::Overflow { options } => write!(
+                f,
+                "The margin of {} and spacing of {} are too large for the grid",
+                options.margin, options.spacing
+            ),
+        }
+    }
+}
+
+impl Error for SliceError {}
+
+/// Splits a grid spritesheet into its cells. Completely transparent cells
+/// are skipped. The anchors describe where the sprites are located in the
+/// sheet, with the row-major index of the cell as their id.
+///
+/// Fails if the margin, spacing and cells of the grid add up to more than
+/// `u32::MAX` pixels.
+pub fn slice_grid(
+    sheet: &InputSprite,
+    stride: usize,
+    alpha_channel_index: usize,
+    options: GridOptions,
+) -> Result<Vec<(SpriteAnchor, InputSprite)>, SliceError> {
+    let overflow = SliceError::Overflow { options };
+    let margin = options.margin;
+    let spacing = options.spacing;
+    let margins = margin.checked_mul(2).ok_or_else(|| overflow.clone())?;
+    let inner_width = sheet.dimensions.0.saturating_sub(margins);
+    let inner_height = sheet.dimensions.1.saturating_sub(margins);
+
+    // Only cells that lie completely inside of the image are used
+    let cells = |inner: u32, cell: u32| {
+        let step = cell.checked_add(spacing)?.max(1);
+        Some(inner.checked_add(spacing)? / step)
+    };
+    let cell_size = |inner: u32, count: u32| {
+        let spacings = count.saturating_sub(1).checked_mul(spacing)?;
+        Some(inner.saturating_sub(spacings) / count.max(1))
+    };
+
+    let (cell_width, cell_height, columns, rows) = match options.size {
+        GridSize::Cell(width, height) => (
+            Some(width),
+            Some(height),
+            cells(inner_width, width),
+            cells(inner_height, height),
+        ),
+        GridSize::Count(columns, rows) => (
+            cell_size(inner_width, columns),
+            cell_size(inner_height, rows),
+            Some(columns),
+            Some(rows),
+        ),
+    };
+    let (cell_width, cell_height, columns, rows) = match (cell_width, cell_height, columns, rows) {
+        (Some(cell_width), Some(cell_height), Some(columns), Some(rows)) => {
+            (cell_width, cell_height, columns, rows)
+        }
+        _ => return Err(overflow),
+    };
+
+    if cell_width == 0 || cell_height == 0 {
+        return Ok(Vec::new());
+    }
+
+    let offset = |index: u32, cell: u32| {
+        cell.checked_add(spacing)
+            .and_then(|step| index.checked_mul(step))
+            .and_then(|start| start.checked_add(margin))
+    };
+
+    let mut result = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let position = match (offset(column, cell_width), offset(row, cell_height)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => return Err(overflow),
+            };
+            let anchor = SpriteAnchor::new(
+                row as usize * columns as usize + column as usize,
+                position,
+                (cell_width, cell_height),
+            );
+
+            let sprite = read_sprite(&sheet.bytes, sheet.dimensions, stride, &anchor)
+                .expect("Unreachable: cells lie inside of the sheet");
+            if !sprite.is_transparent(stride, alpha_channel_index) {
+                result.push((anchor, sprite));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds sprites scattered on a transparent sheet. Every group of
+/// connected opaque pixels becomes a sprite, and groups that are at most
+/// `distance` pixels apart are merged into a single sprite, e.g. to keep
+/// a character's detached weapon together with the character.
+///
+/// The anchors describe where the sprites are located in the sheet, with
+/// sprites ordered top to bottom, then left to right. Pixels within a
+/// sprite's bounds that belong to a different sprite are left transparent.
+pub fn detect_sprites(
+    sheet: &InputSprite,
+    stride: usize,
+    alpha_channel_index: usize,
+    distance: u32,
+) -> Vec<(SpriteAnchor, InputSprite)> {
+    let (width, height) = sheet.dimensions;
+    let opaque = |x: u32, y: u32| sheet.is_opaque(x, y, stride as u32, alpha_channel_index as u32);
+
+    // Label all connected pixels using a flood fill. Label 0 is transparent.
+    let mut labels = vec![0usize; (width * height) as usize];
+    let mut bounds = Vec::<Bounds>::new();
+    let mut stack = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if labels[(y * width + x) as usize] != 0 || !opaque(x, y) {
+                continue;
+            }
+
+            let label = bounds.len() + 1;
+            let mut component = Bounds::new(x, y);
+            labels[(y * width + x) as usize] = label;
+            stack.push((x, y));
+
+            while let Some((x, y)) = stack.pop() {
+                component.extend(x, y);
+
+                let min_x = x.saturating_sub(1);
+                let min_y = y.saturating_sub(1);
+                let max_x = (x + 1).min(width - 1);
+                let max_y = (y + 1).min(height - 1);
+
+                for ny in min_y..=max_y {
+                    for nx in min_x..=max_x {
+                        let index = (ny * width + nx) as usize;
+                        if labels[index] == 0 && opaque(nx, ny) {
+                            labels[index] = label;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+
+            bounds.push(component);
+        }
+    }
+
+    // Merge components that are close to each other. They are swept from
+    // left to right, so every component is only compared with the ones that
+    // end at most `distance` pixels before it starts. Growing bounds can
+    // bring further components into range, so this repeats until nothing
+    // changes.
+    let mut groups = (0..bounds.len()).collect::<Vec<usize>>();
+    let mut merged = bounds.clone();
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        let mut roots = (0..merged.len())
+            .filter(|i| groups[*i] == *i)
+            .collect::<Vec<usize>>();
+        roots.sort_by_key(|root| merged[*root].min_x);
+
+        let mut active = Vec::<usize>::new();
+        for root in roots {
+            let min_x = u64::from(merged[root].min_x);
+            active.retain(|other| u64::from(merged[*other].max_x) + u64::from(distance) >= min_x);
+
+            let (close, far) = active
+                .drain(..)
+                .partition::<Vec<usize>, _>(|other| merged[*other].gap(&merged[root]) <= distance);
+            for other in close {
+                merged[root] = merged[root].union(&merged[other]);
+                groups[other] = root;
+                changed = true;
+            }
+
+            active = far;
+            active.push(root);
+        }
+    }
+
+    // Point every component directly at the group it ended up in
+    for i in 0..groups.len() {
+        let mut group = groups[i];
+        while groups[group] != group {
+            group = groups[group];
+        }
+        groups[i] = group;
+    }
+
+    let mut result = (0..merged.len())
+        .filter(|i| groups[*i] == *i)
+        .map(|group| {
+            let area = merged[group];
+            let dimensions = (area.max_x - area.min_x, area.max_y - area.min_y);
+            let mut bytes = Vec::with_capacity((dimensions.0 * dimensions.1) as usize * stride);
+
+            for y in area.min_y..area.max_y {
+                for x in area.min_x..area.max_x {
+                    let label = labels[(y * width + x) as usize];
+                    let start = (y * width + x) as usize * stride;
+
+                    if label != 0 && groups[label - 1] == group {
+                        bytes.extend_from_slice(&sheet.bytes[start..start + stride]);
+                    } else {
+                        bytes.extend((0..stride).map(|_| 0));
+                    }
+                }
+            }
+
+            let anchor = SpriteAnchor::new(0, (area.min_x, area.min_y), dimensions);
+            (anchor, InputSprite { bytes, dimensions })
+        })
+        .collect::<Vec<(SpriteAnchor, InputSprite)>>();
+
+    result.sort_by_key(|(anchor, _)| (anchor.position.1, anchor.position.0));
+    for (id, (anchor, _)) in result.iter_mut().enumerate() {
+        anchor.id = id;
+    }
+
+    result
+}
+
+// Bounding box of a group of pixels, not including max_x and max_y
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Bounds {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+}
+
+impl Bounds {
+    fn new(x: u32, y: u32) -> Self {
+        Bounds {
+            min_x: x,
+            min_y: y,
+            max_x: x + 1,
+            max_y: y + 1,
+        }
+    }
+
+    fn extend(&mut self, x: u32, y: u32) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x + 1);
+        self.max_y = self.max_y.max(y + 1);
+    }
+
+    fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    // Number of pixels between the two boxes along the axis where they are
+    // furthest apart, 0 if they touch or overlap
+    fn gap(&self, other: &Bounds) -> u32 {
+        let gap_x = other
+            .min_x
+            .saturating_sub(self.max_x)
+            .max(self.min_x.saturating_sub(other.max_x));
+        let gap_y = other
+            .min_y
+            .saturating_sub(self.max_y)
+            .max(self.min_y.saturating_sub(other.max_y));
+
+        gap_x.max(gap_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2x2 grid of 2x2 cells with a margin of 1 and spacing of 1, where the
+    // top right cell is empty. Every pixel is its own cell index + 1.
+    fn grid_sheet() -> InputSprite {
+        let dimensions = (7, 7);
+        let mut bytes = vec![0; 7 * 7];
+        for (cell, (x, y)) in [(1, 1), (4, 1), (1, 4), (4, 4)].iter().enumerate() {
+            if cell == 1 {
+                continue;
+            }
+
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    bytes[(y + dy) * 7 + x + dx] = cell as u8 + 1;
+                }
+            }
+        }
+
+        InputSprite { bytes, dimensions }
+    }
+
+    #[test]
+    fn slice_by_cell_size() {
+        let options = GridOptions::cell_size(2, 2).margin(1).spacing(1);
+        let sliced = slice_grid(&grid_sheet(), 1, 0, options).unwrap();
+
+        let ids = sliced
+            .iter()
+            .map(|(anchor, _)| anchor.id)
+            .collect::<Vec<usize>>();
+        assert_eq!(ids, vec![0, 2, 3]);
+
+        let (anchor, sprite) = &sliced[2];
+        assert_eq!(anchor.position, (4, 4));
+        assert_eq!(sprite.dimensions, (2, 2));
+        assert_eq!(sprite.bytes, vec![4; 4]);
+    }
+
+    #[test]
+    fn slice_by_cell_count() {
+        let options = GridOptions::cell_count(2, 2).margin(1).spacing(1);
+        let by_count = slice_grid(&grid_sheet(), 1, 0, options).unwrap();
+
+        let options = GridOptions::cell_size(2, 2).margin(1).spacing(1);
+        let by_size = slice_grid(&grid_sheet(), 1, 0, options).unwrap();
+
+        assert_eq!(by_count.len(), by_size.len());
+        for ((a, _), (b, _)) in by_count.iter().zip(&by_size) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.dimensions, b.dimensions);
+        }
+    }
+
+    #[test]
+    fn slice_overflowing_grid() {
+        let options = GridOptions::cell_size(2, 2).margin(u32::MAX / 2 + 1);
+        assert_eq!(
+            slice_grid(&grid_sheet(), 1, 0, options).err(),
+            Some(SliceError::Overflow { options })
+        );
+
+        let options = GridOptions::cell_size(2, 2).spacing(u32::MAX);
+        assert_eq!(
+            slice_grid(&grid_sheet(), 1, 0, options).err(),
+            Some(SliceError::Overflow { options })
+        );
+
+        let options = GridOptions::cell_count(3, 3).spacing(u32::MAX / 2 + 1);
+        assert_eq!(
+            slice_grid(&grid_sheet(), 1, 0, options).err(),
+            Some(SliceError::Overflow { options })
+        );
+    }
+
+    // Two islands close to each other in the top left, one far away in
+    // the bottom right. Only the alpha channel is used.
+    fn loose_sheet() -> InputSprite {
+        #[rustfmt::skip]
+        let bytes = vec![
+            1, 1, 0, 1, 0, 0, 0, 0,
+            1, 0, 0, 1, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 1, 0,
+            0, 0, 0, 0, 0, 1, 1, 1,
+        ];
+
+        InputSprite {
+            bytes,
+            dimensions: (8, 6),
+        }
+    }
+
+    #[test]
+    fn detect_islands() {
+        let detected = detect_sprites(&loose_sheet(), 1, 0, 0);
+        let areas = detected
+            .iter()
+            .map(|(anchor, _)| (anchor.id, anchor.position, anchor.dimensions))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            areas,
+            vec![
+                (0, (0, 0), (2, 2)),
+                (1, (3, 0), (1, 2)),
+                (2, (5, 4), (3, 2)),
+            ]
+        );
+        assert_eq!(detected[0].1.bytes, vec![1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn detect_merges_nearby_islands() {
+        let detected = detect_sprites(&loose_sheet(), 1, 0, 1);
+        let areas = detected
+            .iter()
+            .map(|(anchor, _)| (anchor.position, anchor.dimensions))
+            .collect::<Vec<_>>();
+
+        assert_eq!(areas, vec![((0, 0), (4, 2)), ((5, 4), (3, 2))]);
+        assert_eq!(detected[0].1.bytes, vec![1, 1, 0, 1, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn detect_merges_chains() {
+        let sheet = InputSprite {
+            bytes: vec![1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 1],
+            dimensions: (11, 1),
+        };
+
+        let detected = detect_sprites(&sheet, 1, 0, 1);
+        assert_eq!(detected.len(), 2);
+        assert_eq!(detected[0].0.dimensions, (7, 1));
+        assert_eq!(detected[1].0.position, (10, 0));
+    }
+
+    #[test]
+    fn detect_transparent() {
+        let sheet = InputSprite {
+            bytes: vec![0; 16],
+            dimensions: (4, 4),
+        };
+
+        assert!(detect_sprites(&sheet, 1, 0, 4).is_empty());
+    }
+}
@@ -1,3 +1,6 @@
+use std::error::Error;
+use std::fmt;
+
 #[derive(Debug, Clone)]
 pub struct InputSprite {
     pub bytes: Vec<u8>,
@@ -5,10 +8,84 @@ pub struct InputSprite {
 }
 
 impl InputSprite {
-    pub fn trimmed(&self, stride: usize, alpha_channel_index: usize) -> InputSprite {
+    /// Returns true if no pixel of the sprite has a non-zero alpha value.
+    pub fn is_transparent(&self, stride: usize, alpha_channel_index: usize) -> bool {
         let stride = stride as u32;
         let alpha_channel_index = alpha_channel_index as u32;
 
+        (0..self.dimensions.1).all(|y| {
+            (0..self.dimensions.0).all(|x| !self.is_opaque(x, y, stride, alpha_channel_index))
+        })
+    }
+
+    pub(crate) fn is_opaque(&self, x: u32, y: u32, stride: u32, alpha_channel_index: u32) -> bool {
+        let index = (y * self.dimensions.0 + x) * stride + alpha_channel_index;
+        self.bytes[index as usize] != 0
+    }
+
+    pub fn trimmed(&self, stride: usize, alpha_channel_index: usize) -> InputSprite {
+        let ((left_ident, top_ident), (right_ident, bottom_ident)) =
+            match self.opaque_bounds(stride as u32, alpha_channel_index as u32) {
+                Some(bounds) => bounds,
+                // NOTE: not sure, maybe return non empty image
+                // in order not to break something unexpectedly?
+                None => {
+                    return InputSprite {
+                        bytes: vec![],
+                        dimensions: (0, 0),
+                    }
+                }
+            };
+        let stride = stride as u32;
+
+        let trimmed_dimensions = (right_ident - left_ident, bottom_ident - top_ident);
+        let mut trimmed_buffer = create_pixel_buffer(trimmed_dimensions, stride as usize);
+        for y in 0..trimmed_dimensions.1 {
+            let sprite_y = (y + top_ident) * self.dimensions.0 * stride;
+            let trimmed_y = y * trimmed_dimensions.0 * stride;
+
+            for x in 0..trimmed_dimensions.0 {
+                let sprite_x = (x + left_ident) * stride;
+                let trimmed_x = x * stride;
+
+                for i in 0..stride {
+                    let sprite_idx = (sprite_y + sprite_x + i) as usize;
+                    let trimmed_idx = (trimmed_y + trimmed_x + i) as usize;
+
+                    trimmed_buffer[trimmed_idx] = self.bytes[sprite_idx];
+                }
+            }
+        }
+
+        InputSprite {
+            bytes: trimmed_buffer,
+            dimensions: trimmed_dimensions,
+        }
+    }
+
+    /// Where `trimmed` cuts the sprite out of this image.
+    pub fn trim_info(&self, stride: usize, alpha_channel_index: usize) -> TrimInfo {
+        // Fully transparent sprites are trimmed down to nothing
+        let (offset, trimmed) = self
+            .opaque_bounds(stride as u32, alpha_channel_index as u32)
+            .map_or(((0, 0), (0, 0)), |((left, top), (right, bottom))| {
+                ((left, top), (right - left, bottom - top))
+            });
+
+        TrimInfo {
+            offset,
+            original: self.dimensions,
+            trimmed,
+        }
+    }
+
+    // The top left corner and the bottom right corner (exclusive) of the
+    // non-transparent pixels, or nothing if the sprite is fully transparent
+    fn opaque_bounds(
+        &self,
+        stride: u32,
+        alpha_channel_index: u32,
+    ) -> Option<((u32, u32), (u32, u32))> {
         let mut top_ident = self.dimensions.1;
         let mut left_ident = self.dimensions.0;
 
@@ -17,11 +94,8 @@ impl InputSprite {
         let mut bottom_ident = 0;
 
         'outer_top: for y in 0..self.dimensions.1 {
-            let row_offset = y * self.dimensions.0 * stride;
-
             for x in 0..self.dimensions.0 {
-                let index = row_offset + x * stride + alpha_channel_index;
-                if self.bytes[index as usize] != 0 {
+                if self.is_opaque(x, y, stride, alpha_channel_index) {
                     left_ident = left_ident.min(x);
                     right_ident = right_ident.max(x + 1);
                     top_ident = y;
@@ -32,12 +106,9 @@ impl InputSprite {
 
         // if whole image is transparent, this loop won't run
         'outer_bottom: for y in (top_ident..self.dimensions.1).rev() {
-            let row_offset = y * self.dimensions.0 * stride;
-
             // reverse for cache friendliness
             for x in (0..self.dimensions.0).rev() {
-                let index = row_offset + x * stride + alpha_channel_index;
-                if self.bytes[index as usize] != 0 {
+                if self.is_opaque(x, y, stride, alpha_channel_index) {
                     left_ident = left_ident.min(x);
                     right_ident = right_ident.max(x + 1);
                     bottom_ident = y + 1;
@@ -48,57 +119,79 @@ impl InputSprite {
 
         // image is completly transparent
         if top_ident == self.dimensions.1 {
-            // NOTE: not sure, maybe return non empty image
-            // in order not to break something unexpectedly?
-            return InputSprite {
-                bytes: vec![],
-                dimensions: (0, 0),
-            };
+            return None;
         }
 
         // left ident can only decrease and right ident can only increase,
         // so we only look at pixels that can change them
         for y in top_ident..bottom_ident {
-            let row_offset = y * self.dimensions.0 * stride;
             for x in 0..left_ident {
-                let index = row_offset + x * stride + alpha_channel_index;
-                if self.bytes[index as usize] != 0 {
+                if self.is_opaque(x, y, stride, alpha_channel_index) {
                     left_ident = x;
                     break;
                 }
             }
 
             for x in (right_ident..self.dimensions.0).rev() {
-                let index = row_offset + x * stride + alpha_channel_index;
-                if self.bytes[index as usize] != 0 {
+                if self.is_opaque(x, y, stride, alpha_channel_index) {
                     right_ident = x + 1;
                     break;
                 }
             }
         }
 
-        let trimmed_dimensions = (right_ident - left_ident, bottom_ident - top_ident);
-        let mut trimmed_buffer = create_pixel_buffer(trimmed_dimensions, stride as usize);
-        for y in 0..trimmed_dimensions.1 {
-            let sprite_y = (y + top_ident) * self.dimensions.0 * stride;
-            let trimmed_y = y * trimmed_dimensions.0 * stride;
+        Some(((left_ident, top_ident), (right_ident, bottom_ident)))
+    }
+}
 
-            for x in 0..trimmed_dimensions.0 {
-                let sprite_x = (x + left_ident) * stride;
-                let trimmed_x = x * stride;
+/// Where a trimmed sprite lies within the image it was trimmed from.
+/// Formats that can restore the untrimmed frame take these indexed by
+/// sprite id, see `trim_info`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimInfo {
+    /// Position of the trimmed sprite within the untrimmed image.
+    pub offset: (u32, u32),
+    /// Dimensions of the untrimmed image.
+    pub original: (u32, u32),
+    /// Dimensions of the trimmed sprite.
+    pub trimmed: (u32, u32),
+}
 
-                for i in 0..stride {
-                    let sprite_idx = (sprite_y + sprite_x + i) as usize;
-                    let trimmed_idx = (trimmed_y + trimmed_x + i) as usize;
+impl TrimInfo {
+    /// The info for a sprite that wasn't trimmed.
+    pub fn untrimmed(dimensions: (u32, u32)) -> Self {
+        TrimInfo {
+            offset: (0, 0),
+            original: dimensions,
+            trimmed: dimensions,
+        }
+    }
 
-                    trimmed_buffer[trimmed_idx] = self.bytes[sprite_idx];
-                }
-            }
+    /// The info for the trimmed sprite after it was scaled to `dimensions`,
+    /// e.g. by a packer that scales down oversized sprites. The offset and
+    /// the untrimmed dimensions are scaled by the same factor.
+    pub fn scaled(&self, dimensions: (u32, u32)) -> Self {
+        if self.trimmed.0 == 0 || self.trimmed.1 == 0 {
+            return *self;
         }
 
-        InputSprite {
-            bytes: trimmed_buffer,
-            dimensions: trimmed_dimensions,
+        let scale = |value: u32, to: u32, from: u32| {
+            let (value, to, from) = (u64::from(value), u64::from(to), u64::from(from));
+            ((value * to + from / 2) / from) as u32
+        };
+        let (width, height) = dimensions;
+        let (from_width, from_height) = self.trimmed;
+
+        TrimInfo {
+            offset: (
+                scale(self.offset.0, width, from_width),
+                scale(self.offset.1, height, from_height),
+            ),
+            original: (
+                scale(self.original.0, width, from_width),
+                scale(self.original.1, height, from_height),
+            ),
+            trimmed: dimensions,
         }
     }
 }
@@ -133,7 +226,7 @@ impl SpriteData {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SpriteAnchor {
     pub id: usize,
     pub position: (u32, u32),
@@ -156,19 +249,32 @@ pub fn create_pixel_buffer(dimensions: (u32, u32), stride: usize) -> Vec<u8> {
 }
 
 pub fn write_sprite(
-    buffer: &mut Vec<u8>,
+    buffer: &mut [u8],
     dimensions: (u32, u32),
     stride: usize,
     sprite: &Sprite,
     anchor: &SpriteAnchor,
 ) {
-    let stride = stride as u32;
+    // Packers may place a sprite smaller than it is to make it fit
+    let scaled;
+    let bytes = if anchor.dimensions == sprite.data.dimensions {
+        &sprite.bytes
+    } else {
+        scaled = downscale(
+            &sprite.bytes,
+            sprite.data.dimensions,
+            anchor.dimensions,
+            stride,
+        );
+        &scaled
+    };
 
-    for y in 0..sprite.data.dimensions.1 {
-        let sprite_y = y * sprite.data.dimensions.0 * stride;
+    let stride = stride as u32;
+    for y in 0..anchor.dimensions.1 {
+        let sprite_y = y * anchor.dimensions.0 * stride;
         let buffer_y = (y + anchor.position.1) * dimensions.0 * stride;
 
-        for x in 0..sprite.data.dimensions.0 {
+        for x in 0..anchor.dimensions.0 {
             let sprite_x = x * stride;
             let buffer_x = (x + anchor.position.0) * stride;
 
@@ -176,16 +282,153 @@ pub fn write_sprite(
                 let sprite_idx = (sprite_y + sprite_x + i) as usize;
                 let buffer_idx = (buffer_y + buffer_x + i) as usize;
 
-                buffer[buffer_idx] = sprite.bytes[sprite_idx];
+                buffer[buffer_idx] = bytes[sprite_idx];
             }
         }
     }
 }
 
+// Every pixel becomes the average of the pixels it covers in the original
+fn downscale(bytes: &[u8], from: (u32, u32), to: (u32, u32), stride: usize) -> Vec<u8> {
+    let mut scaled = create_pixel_buffer(to, stride);
+    if from.0 == 0 || from.1 == 0 {
+        return scaled;
+    }
+
+    // The range of original pixels along one axis that pixel `i` covers
+    let span = |i: u32, from: u32, to: u32| {
+        let start = u64::from(i) * u64::from(from) / u64::from(to);
+        let end = (u64::from(i + 1) * u64::from(from)).div_ceil(u64::from(to));
+        (start as u32)..(end.max(start + 1) as u32)
+    };
+
+    for y in 0..to.1 {
+        for x in 0..to.0 {
+            let mut sums = vec![0u64; stride];
+            let mut count = 0;
+            for source_y in span(y, from.1, to.1) {
+                for source_x in span(x, from.0, to.0) {
+                    let index = (source_y as usize * from.0 as usize + source_x as usize) * stride;
+                    for (sum, value) in sums.iter_mut().zip(&bytes[index..index + stride]) {
+                        *sum += u64::from(*value);
+                    }
+                    count += 1;
+                }
+            }
+
+            let index = (y as usize * to.0 as usize + x as usize) * stride;
+            for (value, sum) in scaled[index..index + stride].iter_mut().zip(&sums) {
+                *value = (sum / count) as u8;
+            }
+        }
+    }
+
+    scaled
+}
+
+/// Why a sprite couldn't be cut out of a sheet, e.g. because the metadata
+/// doesn't belong to the sheet image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnpackError {
+    /// The anchor reaches past the right or bottom edge of the sheet.
+    OutOfBounds { id: usize, sheet: (u32, u32) },
+    /// The sheet has fewer bytes than its dimensions and stride require.
+    BufferSize { expected: u64, actual: usize },
+}
+
+impl fmt::Display for UnpackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnpackError::OutOfBounds { id, sheet } => write!(
+                f,
+                "Sprite {} lies outside of the {}x{} sheet",
+                id, sheet.0, sheet.1
+            ),
+            UnpackError::BufferSize { expected, actual } => write!(
+                f,
+                "The sheet should have {} bytes, but has {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl Error for UnpackError {}
+
+pub fn read_sprite(
+    buffer: &[u8],
+    dimensions: (u32, u32),
+    stride: usize,
+    anchor: &SpriteAnchor,
+) -> Result<InputSprite, UnpackError> {
+    let expected = u64::from(dimensions.0) * u64::from(dimensions.1) * stride as u64;
+    if (buffer.len() as u64) < expected {
+        return Err(UnpackError::BufferSize {
+            expected,
+            actual: buffer.len(),
+        });
+    }
+
+    let right = u64::from(anchor.position.0) + u64::from(anchor.dimensions.0);
+    let bottom = u64::from(anchor.position.1) + u64::from(anchor.dimensions.1);
+    if right > u64::from(dimensions.0) || bottom > u64::from(dimensions.1) {
+        return Err(UnpackError::OutOfBounds {
+            id: anchor.id,
+            sheet: dimensions,
+        });
+    }
+
+    let row_length = anchor.dimensions.0 as usize * stride;
+    let mut bytes = Vec::with_capacity(row_length * anchor.dimensions.1 as usize);
+
+    for y in 0..anchor.dimensions.1 {
+        let buffer_y = (y + anchor.position.1) as usize * dimensions.0 as usize * stride;
+        let buffer_x = anchor.position.0 as usize * stride;
+        let start = buffer_y + buffer_x;
+
+        bytes.extend_from_slice(&buffer[start..start + row_length]);
+    }
+
+    Ok(InputSprite {
+        bytes,
+        dimensions: anchor.dimensions,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn downscale_averages() {
+        let bytes = vec![0, 40, 100, 200, 20, 60, 200, 0];
+        assert_eq!(downscale(&bytes, (2, 2), (1, 1), 2), vec![80, 75]);
+        assert_eq!(downscale(&bytes, (2, 2), (1, 2), 2), vec![50, 120, 110, 30]);
+    }
+
+    #[test]
+    fn read_out_of_bounds() {
+        let buffer = vec![0; 10 * 10 * 4];
+        let anchor = SpriteAnchor::new(3, (5, u32::MAX), (5, 2));
+        assert_eq!(
+            read_sprite(&buffer, (10, 10), 4, &anchor).map(|sprite| sprite.dimensions),
+            Err(UnpackError::OutOfBounds {
+                id: 3,
+                sheet: (10, 10)
+            })
+        );
+
+        let anchor = SpriteAnchor::new(0, (5, 5), (5, 5));
+        assert!(read_sprite(&buffer, (10, 10), 4, &anchor).is_ok());
+        assert_eq!(
+            read_sprite(&buffer[1..], (10, 10), 4, &anchor).map(|sprite| sprite.dimensions),
+            Err(UnpackError::BufferSize {
+                expected: 400,
+                actual: 399
+            })
+        );
+    }
+
     #[test]
     fn trim_fully_transparent() {
         let dimensions = (2, 2);
@@ -265,8 +508,48 @@ mod tests {
         .cloned()
         .collect();
 
-        let trimmed_sprite = InputSprite { dimensions, bytes }.trimmed(4, 2);
+        let sprite = InputSprite { dimensions, bytes };
+        let trimmed_sprite = sprite.trimmed(4, 2);
         assert_eq!(trimmed_sprite.bytes, expected);
         assert_eq!(trimmed_sprite.dimensions, (2, 2));
+
+        let info = sprite.trim_info(4, 2);
+        assert_eq!(info.offset, (1, 0));
+        assert_eq!(info.original, (4, 2));
+        assert_eq!(info.trimmed, (2, 2));
+    }
+
+    #[test]
+    fn scale_trim_info() {
+        let info = TrimInfo {
+            offset: (4, 2),
+            original: (20, 10),
+            trimmed: (10, 6),
+        };
+
+        let scaled = info.scaled((5, 3));
+        assert_eq!(scaled.offset, (2, 1));
+        assert_eq!(scaled.original, (10, 5));
+        assert_eq!(scaled.trimmed, (5, 3));
+        assert_eq!(info.scaled((10, 6)), info);
+    }
+
+    #[test]
+    fn read_written_sprite() {
+        let sprite = Sprite::from_input(
+            0,
+            InputSprite {
+                bytes: vec![1, 2, 3, 4, 5, 6],
+                dimensions: (3, 2),
+            },
+        );
+        let anchor = SpriteAnchor::new(0, (1, 2), (3, 2));
+
+        let mut buffer = create_pixel_buffer((4, 4), 1);
+        write_sprite(&mut buffer, (4, 4), 1, &sprite, &anchor);
+        let read = read_sprite(&buffer, (4, 4), 1, &anchor).expect("Failed to read sprite");
+
+        assert_eq!(read.bytes, sprite.bytes);
+        assert_eq!(read.dimensions, sprite.data.dimensions);
     }
 }
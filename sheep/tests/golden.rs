@@ -0,0 +1,296 @@
+//! Packs the images in `tests/fixtures` with every packer, encodes the
+//! sheets in every format and compares the results with the files in
+//! `tests/golden`, so any change to the output is noticed.
+//!
+//! After an intended change, run the tests with `SHEEP_BLESS=1` to replace
+//! the golden files with the current output, and review the diff.
+
+extern crate image;
+#[cfg(feature = "amethyst")]
+extern crate ron;
+#[cfg(feature = "amethyst")]
+extern crate serde;
+extern crate sheep;
+
+use sheep::{
+    Cocos2dFormat, Cocos2dOptions, CssFormat, CssOptions, InputSprite, LibGdxFormat, LibGdxOptions,
+    MaxrectsOptions, MaxrectsPacker, Packer, RustFormat, RustOptions, SimpleOptions, SimplePacker,
+    SimpleStrategy, SparrowFormat, SparrowOptions, SpriteAnchor, SpriteSheet,
+    StableMaxrectsOptions, StableMaxrectsPacker, TrimInfo, UnityFormat,
+};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> (Vec<InputSprite>, Vec<String>) {
+    let (sprites, names) = untrimmed_fixtures();
+    (sheep::trim(&sprites, 4, 3), names)
+}
+
+fn untrimmed_fixtures() -> (Vec<InputSprite>, Vec<String>) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut paths = fs::read_dir(&dir)
+        .expect("Failed to read fixtures")
+        .map(|entry| entry.expect("Failed to read fixture").path())
+        .filter(|path| path.extension().map(|ext| ext == "png").unwrap_or(false))
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+
+    let sprites = paths
+        .iter()
+        .map(|path| {
+            let image = image::open(path).expect("Failed to open fixture").to_rgba();
+            InputSprite {
+                dimensions: image.dimensions(),
+                bytes: image.into_raw(),
+            }
+        })
+        .collect::<Vec<InputSprite>>();
+
+    let names = paths
+        .iter()
+        .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+        .collect();
+
+    (sprites, names)
+}
+
+fn golden_path(case: &str, file: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(case)
+        .join(file)
+}
+
+fn bless() -> bool {
+    env::var_os("SHEEP_BLESS").is_some()
+}
+
+fn check_text(case: &str, file: &str, actual: &str) {
+    let path = golden_path(case, file);
+    if bless() {
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create golden directory");
+        fs::write(&path, actual).expect("Failed to write golden file");
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Missing golden file {}", path.display()));
+    assert!(
+        expected == actual,
+        "{} differs from the golden file, run with SHEEP_BLESS=1 to update it\n\
+         expected:\n{}\nactual:\n{}",
+        path.display(),
+        expected,
+        actual
+    );
+}
+
+// Sheets are compared by their pixels, since the png encoding itself is up
+// to the image crate
+fn check_sheet(case: &str, file: &str, sheet: &SpriteSheet) {
+    let path = golden_path(case, file);
+    if bless() {
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create golden directory");
+        image::save_buffer(
+            &path,
+            &sheet.bytes,
+            sheet.dimensions.0,
+            sheet.dimensions.1,
+            image::RGBA(8),
+        )
+        .expect("Failed to write golden sheet");
+        return;
+    }
+
+    let expected = image::open(&path)
+        .unwrap_or_else(|_| panic!("Missing golden file {}", path.display()))
+        .to_rgba();
+    assert_eq!(
+        expected.dimensions(),
+        sheet.dimensions,
+        "{} differs from the golden file, run with SHEEP_BLESS=1 to update it",
+        path.display()
+    );
+    assert!(
+        expected.into_raw() == sheet.bytes,
+        "{} differs from the golden file, run with SHEEP_BLESS=1 to update it",
+        path.display()
+    );
+}
+
+fn pack_twice<P: Packer>(sprites: &[InputSprite], options: P::Options) -> Vec<SpriteSheet>
+where
+    P::Options: Clone,
+{
+    let sheets = sheep::pack::<P>(sprites.to_vec(), 4, options.clone());
+    let again = sheep::pack::<P>(sprites.to_vec(), 4, options);
+
+    assert_eq!(sheets.len(), again.len());
+    for (sheet, other) in sheets.iter().zip(&again) {
+        assert_eq!(sheet.dimensions, other.dimensions);
+        assert_eq!(sheet.anchors(), other.anchors());
+        assert!(sheet.bytes == other.bytes);
+    }
+
+    sheets
+}
+
+// Only the libGDX, Cocos2d and Sparrow formats describe how sprites were
+// trimmed, `trims` can be empty to leave that out
+fn check_formats(case: &str, sheets: &[SpriteSheet], names: &[String], trims: &[TrimInfo]) {
+    let pages = (0..sheets.len())
+        .map(|i| format!("sheet-{}.png", i))
+        .collect::<Vec<String>>();
+
+    for (i, (sheet, page)) in sheets.iter().zip(&pages).enumerate() {
+        check_sheet(case, page, sheet);
+        let stem = format!("sheet-{}", i);
+
+        let cocos2d = sheep::encode::<Cocos2dFormat>(
+            sheet,
+            Cocos2dOptions::new(page.clone(), names.to_vec()).trims(trims.to_vec()),
+        );
+        check_text(case, &format!("{}.plist", stem), &cocos2d);
+
+        let sparrow = sheep::encode::<SparrowFormat>(
+            sheet,
+            SparrowOptions::new(page.clone(), names.to_vec()).trims(trims.to_vec()),
+        );
+        check_text(case, &format!("{}.xml", stem), &sparrow);
+
+        let unity = sheep::encode::<UnityFormat>(sheet, names.to_vec());
+        check_text(case, &format!("{}.meta", stem), &unity);
+
+        let css = sheep::encode::<CssFormat>(
+            sheet,
+            CssOptions::new(page.clone(), names.to_vec()).html(true),
+        );
+        check_text(case, &format!("{}.css", stem), &css.css);
+        check_text(case, &format!("{}.html", stem), &css.html.unwrap());
+
+        let rust = sheep::encode::<RustFormat>(sheet, RustOptions::new(names.to_vec()));
+        check_text(case, &format!("{}.rs", stem), &rust);
+
+        #[cfg(feature = "amethyst")]
+        check_amethyst_formats(case, &stem, sheet, names);
+    }
+
+    let libgdx = sheep::encode_all::<LibGdxFormat>(
+        sheets,
+        LibGdxOptions::new(pages.clone(), names.to_vec()).trims(trims.to_vec()),
+    );
+    check_text(case, "sheet.atlas", &libgdx);
+
+    #[cfg(feature = "amethyst")]
+    {
+        use sheep::{IndexFormat, IndexOptions};
+
+        let index = sheep::encode_all::<IndexFormat>(
+            sheets,
+            IndexOptions::new(pages).names(names.to_vec()),
+        );
+        check_text(case, "sheet-index.ron", &to_ron(&index));
+    }
+}
+
+#[cfg(feature = "amethyst")]
+fn check_amethyst_formats(case: &str, stem: &str, sheet: &SpriteSheet, names: &[String]) {
+    use sheep::{AmethystFormat, AmethystNamedFormat, UvFormat, UvOptions};
+
+    let amethyst = sheep::encode::<AmethystFormat>(sheet, ());
+    check_text(case, &format!("{}.amethyst.ron", stem), &to_ron(&amethyst));
+
+    let named = sheep::encode::<AmethystNamedFormat>(sheet, names.to_vec());
+    check_text(case, &format!("{}.named.ron", stem), &to_ron(&named));
+
+    let uv = sheep::encode::<UvFormat>(sheet, UvOptions::default().names(names.to_vec()));
+    check_text(case, &format!("{}.uv.ron", stem), &to_ron(&uv));
+}
+
+#[cfg(feature = "amethyst")]
+fn to_ron<T: serde::Serialize>(value: &T) -> String {
+    ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .expect("Failed to serialize metadata")
+}
+
+#[test]
+fn golden_simple() {
+    let (sprites, names) = fixtures();
+    let sheets = pack_twice::<SimplePacker>(&sprites, SimpleOptions::default());
+    check_formats("simple", &sheets, &names, &[]);
+}
+
+#[test]
+fn golden_simple_rows() {
+    let (sprites, names) = fixtures();
+    let options = SimpleOptions::default()
+        .max_width(40)
+        .max_height(40)
+        .padding(1)
+        .strategy(SimpleStrategy::Rows);
+    let sheets = pack_twice::<SimplePacker>(&sprites, options);
+
+    assert!(
+        sheets.len() > 1,
+        "Expected the fixtures to need several pages"
+    );
+    check_formats("simple_rows", &sheets, &names, &[]);
+}
+
+#[test]
+fn golden_maxrects() {
+    let (sprites, names) = fixtures();
+    let options = MaxrectsOptions::default()
+        .max_width(40)
+        .max_height(40)
+        .padding(1);
+    let sheets = pack_twice::<MaxrectsPacker>(&sprites, options);
+
+    assert!(
+        sheets.len() > 1,
+        "Expected the fixtures to need several pages"
+    );
+    check_formats("maxrects", &sheets, &names, &[]);
+}
+
+#[test]
+fn golden_stable_maxrects() {
+    let (sprites, names) = fixtures();
+
+    // Keeps `crate` at its previous position in the bottom right corner,
+    // while `gem` was resized since and has to be placed again
+    let crate_id = names.iter().position(|name| name == "crate").unwrap();
+    let gem_id = names.iter().position(|name| name == "gem").unwrap();
+    let previous = vec![
+        SpriteAnchor::new(crate_id, (48, 48), (16, 16)),
+        SpriteAnchor::new(gem_id, (0, 0), (4, 4)),
+    ];
+    let options = StableMaxrectsOptions::new(
+        MaxrectsOptions::default().max_width(64).max_height(64),
+        previous,
+    );
+    let sheets = pack_twice::<StableMaxrectsPacker>(&sprites, options);
+
+    let anchor = sheets[0].anchors()[crate_id];
+    assert_eq!(anchor.position, (48, 48));
+    check_formats("stable_maxrects", &sheets, &names, &[]);
+}
+
+#[test]
+fn golden_trimmed() {
+    let (sprites, names) = untrimmed_fixtures();
+    let trims = sheep::trim_info(&sprites, 4, 3);
+    assert!(
+        trims
+            .iter()
+            .zip(&sprites)
+            .any(|(trim, sprite)| *trim != TrimInfo::untrimmed(sprite.dimensions)),
+        "Expected some fixtures to have transparent borders"
+    );
+
+    let sprites = sheep::trim(&sprites, 4, 3);
+    let options = MaxrectsOptions::default().max_width(64).max_height(64);
+    let sheets = pack_twice::<MaxrectsPacker>(&sprites, options);
+    check_formats("trimmed", &sheets, &names, &trims);
+}
@@ -0,0 +1,86 @@
+// Generated by sheep, do not edit.
+
+pub const TEXTURE_WIDTH: u32 = 39;
+pub const TEXTURE_HEIGHT: u32 = 39;
+
+pub const COIN_RECT: [u32; 4] = [0, 31, 8, 8];
+pub const COIN_UV: [f32; 4] = [0.0, 0.7948718, 0.20512821, 1.0];
+
+pub const COIN_COPY_RECT: [u32; 4] = [0, 31, 8, 8];
+pub const COIN_COPY_UV: [f32; 4] = [0.0, 0.7948718, 0.20512821, 1.0];
+
+pub const CRATE_RECT: [u32; 4] = [0, 9, 16, 16];
+pub const CRATE_UV: [f32; 4] = [0.0, 0.23076923, 0.41025642, 0.64102566];
+
+pub const FLAG_RECT: [u32; 4] = [9, 31, 10, 6];
+pub const FLAG_UV: [f32; 4] = [0.23076923, 0.7948718, 0.4871795, 0.94871795];
+
+pub const FLOOR_RECT: [u32; 4] = [0, 0, 32, 8];
+pub const FLOOR_UV: [f32; 4] = [0.0, 0.0, 0.82051283, 0.20512821];
+
+pub const GEM_RECT: [u32; 4] = [20, 31, 5, 5];
+pub const GEM_UV: [f32; 4] = [0.51282054, 0.7948718, 0.64102566, 0.9230769];
+
+pub const PILLAR_RECT: [u32; 4] = [33, 0, 6, 30];
+pub const PILLAR_UV: [f32; 4] = [0.84615386, 0.0, 1.0, 0.7692308];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sprites {
+    Coin,
+    CoinCopy,
+    Crate,
+    Flag,
+    Floor,
+    Gem,
+    Pillar,
+}
+
+impl Sprites {
+    pub const ALL: [Sprites; 7] = [
+        Sprites::Coin,
+        Sprites::CoinCopy,
+        Sprites::Crate,
+        Sprites::Flag,
+        Sprites::Floor,
+        Sprites::Gem,
+        Sprites::Pillar,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sprites::Coin => "coin",
+            Sprites::CoinCopy => "coin_copy",
+            Sprites::Crate => "crate",
+            Sprites::Flag => "flag",
+            Sprites::Floor => "floor",
+            Sprites::Gem => "gem",
+            Sprites::Pillar => "pillar",
+        }
+    }
+
+    /// `[x, y, width, height]` in pixels
+    pub fn rect(self) -> [u32; 4] {
+        match self {
+            Sprites::Coin => COIN_RECT,
+            Sprites::CoinCopy => COIN_COPY_RECT,
+            Sprites::Crate => CRATE_RECT,
+            Sprites::Flag => FLAG_RECT,
+            Sprites::Floor => FLOOR_RECT,
+            Sprites::Gem => GEM_RECT,
+            Sprites::Pillar => PILLAR_RECT,
+        }
+    }
+
+    /// `[u0, v0, u1, v1]`, with the origin in the top left corner
+    pub fn uv(self) -> [f32; 4] {
+        match self {
+            Sprites::Coin => COIN_UV,
+            Sprites::CoinCopy => COIN_COPY_UV,
+            Sprites::Crate => CRATE_UV,
+            Sprites::Flag => FLAG_UV,
+            Sprites::Floor => FLOOR_UV,
+            Sprites::Gem => GEM_UV,
+            Sprites::Pillar => PILLAR_UV,
+        }
+    }
+}
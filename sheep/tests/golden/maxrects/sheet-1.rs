@@ -0,0 +1,38 @@
+// Generated by sheep, do not edit.
+
+pub const TEXTURE_WIDTH: u32 = 24;
+pub const TEXTURE_HEIGHT: u32 = 10;
+
+pub const BANNER_RECT: [u32; 4] = [0, 0, 24, 10];
+pub const BANNER_UV: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sprites {
+    Banner,
+}
+
+impl Sprites {
+    pub const ALL: [Sprites; 1] = [
+        Sprites::Banner,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sprites::Banner => "banner",
+        }
+    }
+
+    /// `[x, y, width, height]` in pixels
+    pub fn rect(self) -> [u32; 4] {
+        match self {
+            Sprites::Banner => BANNER_RECT,
+        }
+    }
+
+    /// `[u0, v0, u1, v1]`, with the origin in the top left corner
+    pub fn uv(self) -> [f32; 4] {
+        match self {
+            Sprites::Banner => BANNER_UV,
+        }
+    }
+}
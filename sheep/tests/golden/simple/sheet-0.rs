@@ -0,0 +1,94 @@
+// Generated by sheep, do not edit.
+
+pub const TEXTURE_WIDTH: u32 = 32;
+pub const TEXTURE_HEIGHT: u32 = 54;
+
+pub const BANNER_RECT: [u32; 4] = [0, 30, 24, 10];
+pub const BANNER_UV: [f32; 4] = [0.0, 0.5555556, 0.75, 0.7407407];
+
+pub const COIN_RECT: [u32; 4] = [22, 0, 8, 8];
+pub const COIN_UV: [f32; 4] = [0.6875, 0.0, 0.9375, 0.14814815];
+
+pub const COIN_COPY_RECT: [u32; 4] = [22, 0, 8, 8];
+pub const COIN_COPY_UV: [f32; 4] = [0.6875, 0.0, 0.9375, 0.14814815];
+
+pub const CRATE_RECT: [u32; 4] = [6, 0, 16, 16];
+pub const CRATE_UV: [f32; 4] = [0.1875, 0.0, 0.6875, 0.2962963];
+
+pub const FLAG_RECT: [u32; 4] = [0, 48, 10, 6];
+pub const FLAG_UV: [f32; 4] = [0.0, 0.8888889, 0.3125, 1.0];
+
+pub const FLOOR_RECT: [u32; 4] = [0, 40, 32, 8];
+pub const FLOOR_UV: [f32; 4] = [0.0, 0.7407407, 1.0, 0.8888889];
+
+pub const GEM_RECT: [u32; 4] = [24, 30, 5, 5];
+pub const GEM_UV: [f32; 4] = [0.75, 0.5555556, 0.90625, 0.6481481];
+
+pub const PILLAR_RECT: [u32; 4] = [0, 0, 6, 30];
+pub const PILLAR_UV: [f32; 4] = [0.0, 0.0, 0.1875, 0.5555556];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sprites {
+    Banner,
+    Coin,
+    CoinCopy,
+    Crate,
+    Flag,
+    Floor,
+    Gem,
+    Pillar,
+}
+
+impl Sprites {
+    pub const ALL: [Sprites; 8] = [
+        Sprites::Banner,
+        Sprites::Coin,
+        Sprites::CoinCopy,
+        Sprites::Crate,
+        Sprites::Flag,
+        Sprites::Floor,
+        Sprites::Gem,
+        Sprites::Pillar,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sprites::Banner => "banner",
+            Sprites::Coin => "coin",
+            Sprites::CoinCopy => "coin_copy",
+            Sprites::Crate => "crate",
+            Sprites::Flag => "flag",
+            Sprites::Floor => "floor",
+            Sprites::Gem => "gem",
+            Sprites::Pillar => "pillar",
+        }
+    }
+
+    /// `[x, y, width, height]` in pixels
+    pub fn rect(self) -> [u32; 4] {
+        match self {
+            Sprites::Banner => BANNER_RECT,
+            Sprites::Coin => COIN_RECT,
+            Sprites::CoinCopy => COIN_COPY_RECT,
+            Sprites::Crate => CRATE_RECT,
+            Sprites::Flag => FLAG_RECT,
+            Sprites::Floor => FLOOR_RECT,
+            Sprites::Gem => GEM_RECT,
+            Sprites::Pillar => PILLAR_RECT,
+        }
+    }
+
+    /// `[u0, v0, u1, v1]`, with the origin in the top left corner
+    pub fn uv(self) -> [f32; 4] {
+        match self {
+            Sprites::Banner => BANNER_UV,
+            Sprites::Coin => COIN_UV,
+            Sprites::CoinCopy => COIN_COPY_UV,
+            Sprites::Crate => CRATE_UV,
+            Sprites::Flag => FLAG_UV,
+            Sprites::Floor => FLOOR_UV,
+            Sprites::Gem => GEM_UV,
+            Sprites::Pillar => PILLAR_UV,
+        }
+    }
+}
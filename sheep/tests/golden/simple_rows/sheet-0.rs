@@ -0,0 +1,78 @@
+// Generated by sheep, do not edit.
+
+pub const TEXTURE_WIDTH: u32 = 33;
+pub const TEXTURE_HEIGHT: u32 = 36;
+
+pub const BANNER_RECT: [u32; 4] = [0, 0, 24, 10];
+pub const BANNER_UV: [f32; 4] = [0.0, 0.0, 0.72727275, 0.2777778];
+
+pub const COIN_RECT: [u32; 4] = [25, 0, 8, 8];
+pub const COIN_UV: [f32; 4] = [0.75757575, 0.0, 1.0, 0.22222222];
+
+pub const COIN_COPY_RECT: [u32; 4] = [25, 0, 8, 8];
+pub const COIN_COPY_UV: [f32; 4] = [0.75757575, 0.0, 1.0, 0.22222222];
+
+pub const CRATE_RECT: [u32; 4] = [0, 11, 16, 16];
+pub const CRATE_UV: [f32; 4] = [0.0, 0.30555555, 0.4848485, 0.75];
+
+pub const FLAG_RECT: [u32; 4] = [17, 11, 10, 6];
+pub const FLAG_UV: [f32; 4] = [0.5151515, 0.30555555, 0.8181818, 0.4722222];
+
+pub const FLOOR_RECT: [u32; 4] = [0, 28, 32, 8];
+pub const FLOOR_UV: [f32; 4] = [0.0, 0.7777778, 0.969697, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sprites {
+    Banner,
+    Coin,
+    CoinCopy,
+    Crate,
+    Flag,
+    Floor,
+}
+
+impl Sprites {
+    pub const ALL: [Sprites; 6] = [
+        Sprites::Banner,
+        Sprites::Coin,
+        Sprites::CoinCopy,
+        Sprites::Crate,
+        Sprites::Flag,
+        Sprites::Floor,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sprites::Banner => "banner",
+            Sprites::Coin => "coin",
+            Sprites::CoinCopy => "coin_copy",
+            Sprites::Crate => "crate",
+            Sprites::Flag => "flag",
+            Sprites::Floor => "floor",
+        }
+    }
+
+    /// `[x, y, width, height]` in pixels
+    pub fn rect(self) -> [u32; 4] {
+        match self {
+            Sprites::Banner => BANNER_RECT,
+            Sprites::Coin => COIN_RECT,
+            Sprites::CoinCopy => COIN_COPY_RECT,
+            Sprites::Crate => CRATE_RECT,
+            Sprites::Flag => FLAG_RECT,
+            Sprites::Floor => FLOOR_RECT,
+        }
+    }
+
+    /// `[u0, v0, u1, v1]`, with the origin in the top left corner
+    pub fn uv(self) -> [f32; 4] {
+        match self {
+            Sprites::Banner => BANNER_UV,
+            Sprites::Coin => COIN_UV,
+            Sprites::CoinCopy => COIN_COPY_UV,
+            Sprites::Crate => CRATE_UV,
+            Sprites::Flag => FLAG_UV,
+            Sprites::Floor => FLOOR_UV,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// Generated by sheep, do not edit.
+
+pub const TEXTURE_WIDTH: u32 = 12;
+pub const TEXTURE_HEIGHT: u32 = 30;
+
+pub const GEM_RECT: [u32; 4] = [0, 0, 5, 5];
+pub const GEM_UV: [f32; 4] = [0.0, 0.0, 0.41666666, 0.16666667];
+
+pub const PILLAR_RECT: [u32; 4] = [6, 0, 6, 30];
+pub const PILLAR_UV: [f32; 4] = [0.5, 0.0, 1.0, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sprites {
+    Gem,
+    Pillar,
+}
+
+impl Sprites {
+    pub const ALL: [Sprites; 2] = [
+        Sprites::Gem,
+        Sprites::Pillar,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sprites::Gem => "gem",
+            Sprites::Pillar => "pillar",
+        }
+    }
+
+    /// `[x, y, width, height]` in pixels
+    pub fn rect(self) -> [u32; 4] {
+        match self {
+            Sprites::Gem => GEM_RECT,
+            Sprites::Pillar => PILLAR_RECT,
+        }
+    }
+
+    /// `[u0, v0, u1, v1]`, with the origin in the top left corner
+    pub fn uv(self) -> [f32; 4] {
+        match self {
+            Sprites::Gem => GEM_UV,
+            Sprites::Pillar => PILLAR_UV,
+        }
+    }
+}
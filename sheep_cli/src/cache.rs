@@ -0,0 +1,302 @@
+use config::{AtlasConfig, Oversized, Strategy};
+use input::InputFile;
+use page_filenames;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use sheep::{SheetStats, SpriteAnchor, SpriteSheet, TrimInfo};
+use std::fs;
+use std::hash::Hasher;
+use std::path::Path;
+use twox_hash::XxHash64;
+
+// Bump this whenever the manifest layout changes, so old manifests are
+// treated as outdated instead of failing to load
+const CACHE_VERSION: u64 = 3;
+
+/// The result of packing an atlas, kept next to the output so later runs
+/// with the same inputs and packing settings can skip loading, packing and
+/// compressing, and only write the metadata again.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CacheManifest {
+    pub key: u64,
+    pub pages: Vec<CachedPage>,
+    pub trims: Vec<CachedTrim>,
+}
+
+/// `image_hash` is the hash of the written png, so pages that were changed
+/// or removed since are detected.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CachedPage {
+    pub image_hash: u64,
+    pub dimensions: (u32, u32),
+    pub anchors: Vec<CachedAnchor>,
+    pub stats: CachedStats,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CachedAnchor {
+    pub id: usize,
+    pub position: (u32, u32),
+    pub dimensions: (u32, u32),
+}
+
+/// Mirrors `sheep::TrimInfo`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CachedTrim {
+    pub offset: (u32, u32),
+    pub original: (u32, u32),
+    pub trimmed: (u32, u32),
+}
+
+impl From<TrimInfo> for CachedTrim {
+    fn from(trim: TrimInfo) -> Self {
+        CachedTrim {
+            offset: trim.offset,
+            original: trim.original,
+            trimmed: trim.trimmed,
+        }
+    }
+}
+
+impl From<CachedTrim> for TrimInfo {
+    fn from(trim: CachedTrim) -> Self {
+        TrimInfo {
+            offset: trim.offset,
+            original: trim.original,
+            trimmed: trim.trimmed,
+        }
+    }
+}
+
+/// Mirrors `sheep::SheetStats`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CachedStats {
+    pub sprites: usize,
+    pub aliases: usize,
+    pub area: u64,
+    pub used_area: u64,
+    pub trimmed_bytes: u64,
+}
+
+impl CachedPage {
+    pub fn new(image_hash: u64, sheet: &SpriteSheet) -> Self {
+        let stats = sheet.stats();
+        CachedPage {
+            image_hash,
+            dimensions: sheet.dimensions,
+            anchors: sheet
+                .anchors()
+                .iter()
+                .map(|anchor| CachedAnchor {
+                    id: anchor.id,
+                    position: anchor.position,
+                    dimensions: anchor.dimensions,
+                })
+                .collect(),
+            stats: CachedStats {
+                sprites: stats.sprites,
+                aliases: stats.aliases,
+                area: stats.area,
+                used_area: stats.used_area,
+                trimmed_bytes: stats.trimmed_bytes,
+            },
+        }
+    }
+
+    pub fn anchors(&self) -> Vec<SpriteAnchor> {
+        self.anchors
+            .iter()
+            .map(|anchor| SpriteAnchor::new(anchor.id, anchor.position, anchor.dimensions))
+            .collect()
+    }
+
+    pub fn stats(&self) -> SheetStats {
+        SheetStats {
+            sprites: self.stats.sprites,
+            aliases: self.stats.aliases,
+            area: self.stats.area,
+            used_area: self.stats.used_area,
+            trimmed_bytes: self.stats.trimmed_bytes,
+        }
+    }
+}
+
+pub fn manifest_path(output: &str) -> String {
+    format!("{}.sheep-cache", output)
+}
+
+/// Hashes everything that influences the output: the contents of the
+/// inputs in order, their names, and the settings used for trimming,
+/// packing and compressing. Metadata formats are left out on purpose,
+/// since all metadata is written again on every run anyway.
+///
+/// The hint file for stable packing is only added by `cache_key`.
+pub fn input_key(config: &AtlasConfig, input: &[InputFile], names: &[String]) -> u64 {
+    let mut hasher = XxHash64::default();
+
+    hasher.write_u64(CACHE_VERSION);
+    hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+    #[cfg(feature = "rayon")]
+    let files = input.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let files = input.iter();
+    let hashes = files
+        .map(|file| hash_file(&file.path).expect("Failed to read input image"))
+        .collect::<Vec<u64>>();
+
+    hasher.write_usize(input.len());
+    for hash in hashes {
+        hasher.write_u64(hash);
+    }
+
+    hasher.write_usize(names.len());
+    for name in names {
+        hasher.write_usize(name.len());
+        hasher.write(name.as_bytes());
+    }
+
+    hasher.write(config.packer.as_bytes());
+    hasher.write_u32(config.options.max_width.unwrap_or(0));
+    hasher.write_u32(config.options.max_height.unwrap_or(0));
+    hasher.write_u8(match config.options.strategy {
+        None => 0,
+        Some(Strategy::Rows) => 1,
+        Some(Strategy::Shelves) => 2,
+    });
+    hasher.write_u64(config.options.max_pages.map_or(0, |pages| pages as u64 + 1));
+    hasher.write_u8(match config.options.oversized {
+        None => 0,
+        Some(Oversized::Separate) => 1,
+        Some(Oversized::Downscale) => 2,
+        Some(Oversized::Error) => 3,
+    });
+    hasher.write_u32(config.padding);
+    hasher.write_u8(config.trim as u8);
+    hasher.write_u8(config.compress as u8);
+
+    hasher.finish()
+}
+
+/// The key the cache is kept under, which is the `input_key` along with the
+/// hint file for stable packing. Stable packing starts from the previous
+/// sheet, so its contents matter as well, and whether it exists yet. The
+/// hint is usually written by packing itself, which is why watch mode only
+/// compares input keys.
+pub fn cache_key(config: &AtlasConfig, input_key: u64) -> u64 {
+    let mut hasher = XxHash64::default();
+
+    hasher.write_u64(input_key);
+    match &config.stable {
+        None => hasher.write_u8(0),
+        Some(path) => match hash_file(Path::new(path)) {
+            None => hasher.write_u8(1),
+            Some(hash) => {
+                hasher.write_u8(2);
+                hasher.write_u64(hash);
+            }
+        },
+    }
+
+    hasher.finish()
+}
+
+pub fn hash_file(path: &Path) -> Option<u64> {
+    let bytes = fs::read(path).ok()?;
+    let mut hasher = XxHash64::default();
+    hasher.write(&bytes);
+    Some(hasher.finish())
+}
+
+/// Returns the cached manifest if it matches the key and all page images
+/// are still as they were written.
+pub fn load_cached(output: &str, key: u64) -> Option<CacheManifest> {
+    let contents = fs::read_to_string(manifest_path(output)).ok()?;
+    let manifest: CacheManifest = ron::de::from_str(&contents).ok()?;
+
+    if manifest.key != key {
+        return None;
+    }
+
+    let unchanged = page_filenames(output, manifest.pages.len())
+        .iter()
+        .zip(&manifest.pages)
+        .all(|(filename, page)| {
+            hash_file(Path::new(&format!("{}.png", filename))) == Some(page.image_hash)
+        });
+
+    if unchanged {
+        Some(manifest)
+    } else {
+        None
+    }
+}
+
+pub fn write_manifest(output: &str, manifest: &CacheManifest) {
+    let contents = ron::ser::to_string(manifest).expect("Failed to serialize cache manifest");
+    fs::write(manifest_path(output), contents).expect("Failed to write cache manifest");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_of(config: &AtlasConfig, names: &[String]) -> u64 {
+        cache_key(config, input_key(config, &[], names))
+    }
+
+    #[test]
+    fn key_ignores_metadata_settings() {
+        let config = AtlasConfig::default();
+        let key = key_of(&config, &[]);
+
+        let metadata = AtlasConfig {
+            formats: vec![String::from("css")],
+            pretty: true,
+            stats: Some(String::from("json")),
+            ..AtlasConfig::default()
+        };
+        assert_eq!(key_of(&metadata, &[]), key);
+
+        let packing = AtlasConfig {
+            padding: 1,
+            ..AtlasConfig::default()
+        };
+        assert_ne!(key_of(&packing, &[]), key);
+    }
+
+    #[test]
+    fn key_covers_stable_hint() {
+        let path = std::env::temp_dir().join(format!("sheep-stable-{}.ron", std::process::id()));
+        let config = AtlasConfig {
+            stable: Some(path.to_string_lossy().into_owned()),
+            ..AtlasConfig::default()
+        };
+
+        let _ = fs::remove_file(&path);
+        let missing = key_of(&config, &[]);
+        assert_ne!(missing, key_of(&AtlasConfig::default(), &[]));
+
+        fs::write(&path, "(sprites: [])").unwrap();
+        let first = key_of(&config, &[]);
+        fs::write(&path, "(sprites: [], texture_width: 1)").unwrap();
+        let second = key_of(&config, &[]);
+        fs::remove_file(&path).unwrap();
+
+        assert_ne!(first, missing);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn key_covers_names() {
+        let config = AtlasConfig::default();
+        let names = vec![String::from("player/idle"), String::from("run")];
+        let key = key_of(&config, &names);
+
+        let renamed = vec![String::from("player/idle"), String::from("walk")];
+        assert_ne!(key_of(&config, &renamed), key);
+
+        // The length is hashed too, so names can't shift into each other
+        let shifted = vec![String::from("player/idler"), String::from("un")];
+        assert_ne!(key_of(&config, &shifted), key);
+    }
+}
@@ -0,0 +1,343 @@
+use input::{InputOptions, NameCollisions};
+use std::fs;
+use std::path::Path;
+use {AVAILABLE_FORMATS, AVAILABLE_PACKERS, DEFAULT_FORMAT, DEFAULT_PACKER};
+
+/// A project file, listing any number of atlases that `sheep build` packs.
+///
+/// In `sheep.toml`, every atlas is an `[[atlas]]` table, while `sheep.ron`
+/// contains a struct with an `atlas` list.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    pub atlas: Vec<AtlasConfig>,
+}
+
+impl Project {
+    /// Reads a project from a `.toml` or `.ron` file. Relative inputs and
+    /// outputs are resolved relative to the directory of the project file.
+    pub fn load(path: &Path) -> Result<Project, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+
+        let project: Project = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents).map_err(|err| err.to_string()),
+            Some("ron") => ron::de::from_str(&contents).map_err(|err| err.to_string()),
+            _ => Err(String::from("Project files have to be .toml or .ron files")),
+        }
+        .map_err(|err| format!("Invalid project file {}: {}", path.display(), err))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        project
+            .atlas
+            .into_iter()
+            .map(|atlas| match atlas.validate() {
+                Ok(()) => Ok(atlas.relative_to(base)),
+                Err(err) => Err(format!("Invalid atlas {}: {}", atlas.output, err)),
+            })
+            .collect::<Result<Vec<AtlasConfig>, String>>()
+            .map(|atlas| Project { atlas })
+    }
+}
+
+/// Everything needed to pack a single atlas, which is either read from a
+/// project file or assembled from the arguments of `sheep pack`. The
+/// fields correspond to the arguments of the same name.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AtlasConfig {
+    pub inputs: Vec<String>,
+    pub recursive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub names: NameCollisions,
+    pub packer: String,
+    pub options: PackerOptions,
+    pub trim: bool,
+    pub padding: u32,
+    pub formats: Vec<String>,
+    pub output: String,
+    pub pretty: bool,
+    pub compress: bool,
+    pub html: bool,
+    pub uv_inset: f32,
+    pub uv_origin: String,
+    pub cache: bool,
+    pub stable: Option<String>,
+    /// Prints how well the sheets are packed, as `human` readable text or
+    /// as `json`.
+    pub stats: Option<String>,
+    pub debug: bool,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        AtlasConfig {
+            inputs: Vec::new(),
+            recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            names: NameCollisions::Error,
+            packer: String::from(DEFAULT_PACKER),
+            options: PackerOptions::default(),
+            trim: false,
+            padding: 0,
+            formats: vec![String::from(DEFAULT_FORMAT)],
+            output: String::from("out"),
+            pretty: false,
+            compress: false,
+            html: false,
+            uv_inset: 0.0,
+            uv_origin: String::from("top_left"),
+            cache: true,
+            stable: None,
+            stats: None,
+            debug: false,
+        }
+    }
+}
+
+impl AtlasConfig {
+    /// Checks the values that aren't already validated by the argument
+    /// parser when coming from a project file.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.inputs.is_empty() {
+            return Err(String::from("No inputs given"));
+        }
+
+        if !AVAILABLE_PACKERS.contains(&self.packer.as_str()) {
+            return Err(format!(
+                "Unknown packer {}, expected one of {}",
+                self.packer,
+                AVAILABLE_PACKERS.join(", ")
+            ));
+        }
+
+        if let Some(format) = self
+            .formats
+            .iter()
+            .find(|format| !AVAILABLE_FORMATS.contains(&format.as_str()))
+        {
+            return Err(format!(
+                "Unknown format {}, expected one of {}",
+                format,
+                AVAILABLE_FORMATS.join(", ")
+            ));
+        }
+
+        // These all write their metadata to `<output>.ron`
+        let ron_formats = self
+            .formats
+            .iter()
+            .filter(|format| ["amethyst", "amethyst_named", "uv"].contains(&format.as_str()))
+            .count();
+        if ron_formats > 1 {
+            return Err(String::from(
+                "Only one of the amethyst, amethyst_named and uv formats can be used at once",
+            ));
+        }
+
+        if self.uv_origin != "top_left" && self.uv_origin != "bottom_left" {
+            return Err(format!(
+                "Unknown uv origin {}, expected top_left or bottom_left",
+                self.uv_origin
+            ));
+        }
+
+        if self.stable.is_some() && self.packer != "maxrects" {
+            return Err(String::from("Stable packing needs the maxrects packer"));
+        }
+
+        match self.stats.as_deref() {
+            None | Some("human") | Some("json") => {}
+            Some(stats) => {
+                return Err(format!(
+                    "Unknown stats format {}, expected human or json",
+                    stats
+                ))
+            }
+        }
+
+        if self.options.strategy.is_some() && self.packer != "simple" {
+            return Err(String::from("Only the simple packer supports a strategy"));
+        }
+
+        if (self.options.max_pages.is_some() || self.options.oversized.is_some())
+            && self.packer != "maxrects"
+        {
+            return Err(String::from(
+                "Only the maxrects packer supports max_pages and oversized",
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn input_options(&self) -> InputOptions {
+        let include = self.include.iter().map(String::as_str).collect::<Vec<_>>();
+        let exclude = self.exclude.iter().map(String::as_str).collect::<Vec<_>>();
+
+        InputOptions::default()
+            .recursive(self.recursive)
+            .include(&include)
+            .exclude(&exclude)
+    }
+
+    fn relative_to(mut self, base: &Path) -> Self {
+        self.inputs = self
+            .inputs
+            .iter()
+            .map(|input| match input.strip_prefix('@') {
+                Some(list) => format!("@{}", base.join(list).display()),
+                None => base.join(input).display().to_string(),
+            })
+            .collect();
+        self.output = base.join(&self.output).display().to_string();
+        self.stable = self
+            .stable
+            .map(|stable| base.join(stable).display().to_string());
+        self
+    }
+}
+
+/// Options passed on to the packer, given as `key=value` pairs on the
+/// command line or as a table in project files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PackerOptions {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub strategy: Option<Strategy>,
+    pub max_pages: Option<usize>,
+    pub oversized: Option<Oversized>,
+}
+
+/// Mirrors `sheep::SimpleStrategy`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    Rows,
+    Shelves,
+}
+
+/// Mirrors `sheep::OversizedPolicy`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Oversized {
+    Separate,
+    Downscale,
+    Error,
+}
+
+impl PackerOptions {
+    pub fn parse(pairs: &[&str]) -> Result<PackerOptions, String> {
+        let mut options = PackerOptions::default();
+
+        for pair in pairs {
+            let mut parts = pair.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => return Err(format!("Expected key=value, found {}", pair)),
+            };
+
+            let number = value
+                .parse::<u32>()
+                .map_err(|_| format!("Expected a number for {}, found {}", key, value));
+
+            match key {
+                "max_width" => options.max_width = Some(number?),
+                "max_height" => options.max_height = Some(number?),
+                "max_pages" => options.max_pages = Some(number? as usize),
+                "oversized" => {
+                    options.oversized = match value {
+                        "separate" => Some(Oversized::Separate),
+                        "downscale" => Some(Oversized::Downscale),
+                        "error" => Some(Oversized::Error),
+                        _ => {
+                            return Err(format!(
+                            "Unknown oversized policy {}, expected separate, downscale or error",
+                            value
+                        ))
+                        }
+                    }
+                }
+                "strategy" => {
+                    options.strategy = match value {
+                        "rows" => Some(Strategy::Rows),
+                        "shelves" => Some(Strategy::Shelves),
+                        _ => {
+                            return Err(format!(
+                                "Unknown strategy {}, expected rows or shelves",
+                                value
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown option {}, expected max_width, max_height, strategy, \
+                         max_pages or oversized",
+                        key
+                    ))
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_packer_options() {
+        let options = PackerOptions::parse(&["max_width=1024", "max_height = 512"]).unwrap();
+        assert_eq!(options.max_width, Some(1024));
+        assert_eq!(options.max_height, Some(512));
+
+        assert!(PackerOptions::parse(&["max_width"]).is_err());
+        assert!(PackerOptions::parse(&["max_width=big"]).is_err());
+        assert!(PackerOptions::parse(&["max_depth=1"]).is_err());
+
+        let options = PackerOptions::parse(&["strategy=rows"]).unwrap();
+        assert_eq!(options.strategy, Some(Strategy::Rows));
+        assert!(PackerOptions::parse(&["strategy=columns"]).is_err());
+
+        let options = PackerOptions::parse(&["max_pages=2", "oversized=downscale"]).unwrap();
+        assert_eq!(options.max_pages, Some(2));
+        assert_eq!(options.oversized, Some(Oversized::Downscale));
+        assert!(PackerOptions::parse(&["oversized=crop"]).is_err());
+    }
+
+    #[test]
+    fn parse_project() {
+        let project: Project = toml::from_str(
+            r#"
+            [[atlas]]
+            inputs = ["sprites/player"]
+            recursive = true
+            formats = ["amethyst_named", "css"]
+            output = "out/player"
+            options = { max_width = 1024 }
+
+            [[atlas]]
+            inputs = ["@ui.txt"]
+            names = "qualify"
+            output = "out/ui"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(project.atlas.len(), 2);
+        assert!(project.atlas.iter().all(|atlas| atlas.validate().is_ok()));
+        assert_eq!(project.atlas[0].options.max_width, Some(1024));
+        assert_eq!(project.atlas[1].names, NameCollisions::Qualify);
+        assert_eq!(project.atlas[1].packer, "maxrects");
+
+        let atlas = project.atlas[1].clone().relative_to(Path::new("assets"));
+        assert_eq!(atlas.inputs, ["@assets/ui.txt"]);
+        assert_eq!(atlas.output, "assets/out/ui");
+    }
+}
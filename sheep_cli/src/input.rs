@@ -0,0 +1,444 @@
+use glob::{glob, Pattern};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Extensions of the image formats the image crate is able to open
+const IMAGE_EXTENSIONS: [&str; 10] = [
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "tif", "tiff", "webp", "tga",
+];
+
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+/// An image to pack, along with the name it's referred to by in the
+/// metadata. The name is the path relative to the directory or glob it was
+/// found through, without the extension, e.g. `player/run_01`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputFile {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+/// What to do when different images end up with the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameCollisions {
+    /// Refuse to continue, listing the colliding images
+    Error,
+    /// Keep the first name and append `_2`, `_3` etc. to the others
+    Suffix,
+    /// Use the full path, as passed to the CLI, for all colliding images
+    Qualify,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InputOptions {
+    recursive: bool,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl InputOptions {
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Only keep files whose relative path matches one of the patterns.
+    pub fn include(mut self, patterns: &[&str]) -> Self {
+        self.include = patterns.iter().map(|it| parse_pattern(it)).collect();
+        self
+    }
+
+    /// Drop files whose relative path matches one of the patterns.
+    pub fn exclude(mut self, patterns: &[&str]) -> Self {
+        self.exclude = patterns.iter().map(|it| parse_pattern(it)).collect();
+        self
+    }
+
+    fn accepts(&self, relative: &str) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(relative));
+        let excluded = self.exclude.iter().any(|pattern| pattern.matches(relative));
+
+        included && !excluded
+    }
+}
+
+/// Expands the arguments into a list of image files. Every argument can be
+/// an image, a directory, a glob pattern, or `@list.txt` to read further
+/// arguments from a file, one per line, relative to the file's location.
+///
+/// The files are kept in argument order, while the files found through a
+/// single directory or glob are sorted by their name. Files that are found
+/// more than once, even through different paths, are only kept the first
+/// time.
+pub fn collect_inputs(args: &[String], options: &InputOptions) -> Vec<InputFile> {
+    let mut inputs = Vec::<InputFile>::new();
+    let mut seen = HashSet::new();
+
+    for arg in args {
+        for input in expand(arg, options) {
+            // Files that don't exist can't be canonicalized, but they are
+            // reported when loading them anyway
+            let canonical = fs::canonicalize(&input.path).unwrap_or_else(|_| input.path.clone());
+            if seen.insert(canonical) {
+                inputs.push(input);
+            }
+        }
+    }
+
+    inputs
+}
+
+/// The paths to watch to notice changes to the inputs given by the
+/// arguments, along with whether they have to be watched recursively.
+/// Files are watched through their directory, since editors often replace
+/// files instead of writing to them.
+pub fn watch_paths(args: &[String], recursive: bool) -> Vec<(PathBuf, bool)> {
+    let mut paths = Vec::<(PathBuf, bool)>::new();
+
+    for arg in args {
+        let found = if let Some(list) = arg.strip_prefix('@') {
+            let list = Path::new(list);
+            let base = list.parent().unwrap_or_else(|| Path::new(""));
+            let entries = fs::read_to_string(list)
+                .map(|contents| {
+                    contents
+                        .lines()
+                        .map(|line| line.trim())
+                        .filter(|line| !line.is_empty() && !line.starts_with(['#', '@']))
+                        .map(|line| base.join(line).to_string_lossy().into_owned())
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default();
+
+            let mut found = vec![(directory_of(list), false)];
+            found.extend(watch_paths(&entries, recursive));
+            found
+        } else if Path::new(arg).is_dir() {
+            vec![(PathBuf::from(arg), recursive)]
+        } else if arg.contains(&GLOB_CHARS[..]) {
+            vec![(glob_base(arg), true)]
+        } else {
+            vec![(directory_of(Path::new(arg)), false)]
+        };
+
+        for (path, recursive) in found {
+            match paths.iter_mut().find(|(it, _)| *it == path) {
+                Some(existing) => existing.1 |= recursive,
+                None => paths.push((path, recursive)),
+            }
+        }
+    }
+
+    paths
+}
+
+fn directory_of(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn expand(arg: &str, options: &InputOptions) -> Vec<InputFile> {
+    if let Some(list) = arg.strip_prefix('@') {
+        let list = Path::new(list);
+        let base = list.parent().unwrap_or_else(|| Path::new(""));
+        let contents = fs::read_to_string(list)
+            .unwrap_or_else(|err| panic!("Failed to read file list {}: {}", list.display(), err));
+
+        // Lists can't include other lists, so they can't loop
+        return contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('@'))
+            .flat_map(|line| expand(&base.join(line).to_string_lossy(), options))
+            .collect();
+    }
+
+    let path = Path::new(arg);
+    let mut inputs = if path.is_dir() {
+        let mut files = Vec::new();
+        walk_dir(path, options.recursive, &mut HashSet::new(), &mut files);
+        files
+            .into_iter()
+            .map(|file| input_file(path, file))
+            .collect::<Vec<InputFile>>()
+    } else if arg.contains(&GLOB_CHARS[..]) {
+        let base = glob_base(arg);
+        glob(arg)
+            .unwrap_or_else(|err| panic!("Invalid glob pattern {}: {}", arg, err))
+            .filter_map(Result::ok)
+            .filter(|file| file.is_file() && is_image(file))
+            .map(|file| input_file(&base, file))
+            .collect::<Vec<InputFile>>()
+    } else {
+        // Explicitly named files are always used, whatever their extension
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        vec![input_file(base, path.to_path_buf())]
+    };
+
+    inputs.retain(|input| options.accepts(&relative_path(&input.path, &input.name)));
+    inputs.sort_by(|a, b| a.name.cmp(&b.name));
+    inputs
+}
+
+/// Returns the names of the inputs, in the same order, making sure that no
+/// two inputs share a name according to the given policy.
+pub fn unique_names(inputs: &[InputFile], policy: NameCollisions) -> Result<Vec<String>, String> {
+    let mut counts = HashMap::<&str, usize>::new();
+    for input in inputs {
+        *counts.entry(&input.name).or_insert(0) += 1;
+    }
+
+    let collides = |input: &InputFile| counts[input.name.as_str()] > 1;
+    let names = match policy {
+        NameCollisions::Error => {
+            let collisions = inputs
+                .iter()
+                .filter(|input| collides(input))
+                .map(|input| format!("    {} ({})", input.name, input.path.display()))
+                .collect::<Vec<String>>();
+
+            if !collisions.is_empty() {
+                return Err(format!(
+                    "Multiple images share the same name:\n{}",
+                    collisions.join("\n")
+                ));
+            }
+
+            inputs.iter().map(|input| input.name.clone()).collect()
+        }
+        NameCollisions::Suffix => inputs.iter().map(|input| input.name.clone()).collect(),
+        NameCollisions::Qualify => inputs
+            .iter()
+            .map(|input| {
+                if collides(input) {
+                    sprite_name(Path::new(""), &input.path)
+                } else {
+                    input.name.clone()
+                }
+            })
+            .collect::<Vec<String>>(),
+    };
+
+    // Qualified names can still collide with other names, so suffixes are
+    // the last resort for every policy
+    Ok(with_suffixes(names))
+}
+
+fn with_suffixes(names: Vec<String>) -> Vec<String> {
+    let mut taken = names.iter().cloned().collect::<HashSet<String>>();
+    let mut seen = HashSet::new();
+
+    names
+        .into_iter()
+        .map(|name| {
+            if seen.insert(name.clone()) {
+                return name;
+            }
+
+            let mut suffix = 2;
+            while taken.contains(&format!("{}_{}", name, suffix)) {
+                suffix += 1;
+            }
+
+            let unique = format!("{}_{}", name, suffix);
+            taken.insert(unique.clone());
+            seen.insert(unique.clone());
+            unique
+        })
+        .collect()
+}
+
+// Symlinks are followed, so directories are only walked the first time
+// they're reached, which also stops symlinks to a parent from looping
+fn walk_dir(dir: &Path, recursive: bool, visited: &mut HashSet<PathBuf>, files: &mut Vec<PathBuf>) {
+    let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    if !visited.insert(canonical) {
+        return;
+    }
+
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("Failed to read directory {}: {}", dir.display(), err));
+
+    for entry in entries {
+        let path = entry.expect("Failed to read directory entry").path();
+        if path.is_dir() {
+            if recursive {
+                walk_dir(&path, recursive, visited, files);
+            }
+        } else if is_image(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|it| it.eq_ignore_ascii_case(ext))
+        })
+        .unwrap_or(false)
+}
+
+// The longest leading part of a glob pattern without any wildcards, which
+// names are made relative to
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(&GLOB_CHARS[..])
+        })
+        .collect()
+}
+
+fn input_file(base: &Path, path: PathBuf) -> InputFile {
+    let name = sprite_name(base, &path);
+    InputFile { path, name }
+}
+
+// Components are always joined by `/`, so names are the same on every
+// platform. Names of absolute paths leave out the root, and e.g. the drive
+// on Windows.
+fn sprite_name(base: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(base).unwrap_or(path).with_extension("");
+
+    relative
+        .components()
+        .filter(|component| match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => false,
+            Component::ParentDir | Component::Normal(_) => true,
+        })
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// Filters match against the name with its original extension
+fn relative_path(path: &Path, name: &str) -> String {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}.{}", name, ext),
+        None => String::from(name),
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Pattern {
+    Pattern::new(pattern).unwrap_or_else(|err| panic!("Invalid pattern {}: {}", pattern, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_names() {
+        assert_eq!(
+            sprite_name(Path::new("assets"), Path::new("assets/player/run_01.png")),
+            "player/run_01"
+        );
+        assert_eq!(sprite_name(Path::new(""), Path::new("idle.png")), "idle");
+        assert_eq!(
+            sprite_name(Path::new(""), Path::new("/assets/idle.png")),
+            "assets/idle"
+        );
+        assert_eq!(sprite_name(Path::new(""), Path::new("./idle.png")), "idle");
+        assert_eq!(glob_base("assets/*/run_*.png"), PathBuf::from("assets"));
+    }
+
+    fn inputs(paths: &[(&str, &str)]) -> Vec<InputFile> {
+        paths
+            .iter()
+            .map(|(path, name)| InputFile {
+                path: PathBuf::from(path),
+                name: String::from(*name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resolve_collisions() {
+        let inputs = inputs(&[
+            ("player/idle.png", "idle"),
+            ("enemies/idle.png", "idle"),
+            ("idle_2.png", "idle_2"),
+            ("run.png", "run"),
+        ]);
+
+        let error = unique_names(&inputs, NameCollisions::Error).unwrap_err();
+        assert!(error.contains("idle (player/idle.png)"));
+        assert!(error.contains("idle (enemies/idle.png)"));
+
+        assert_eq!(
+            unique_names(&inputs, NameCollisions::Suffix).unwrap(),
+            ["idle", "idle_3", "idle_2", "run"]
+        );
+        assert_eq!(
+            unique_names(&inputs, NameCollisions::Qualify).unwrap(),
+            ["player/idle", "enemies/idle", "idle_2", "run"]
+        );
+    }
+
+    #[test]
+    fn filter_relative_paths() {
+        let options = InputOptions::default()
+            .include(&["player/**"])
+            .exclude(&["**/*_old.png"]);
+
+        assert!(options.accepts("player/run_01.png"));
+        assert!(!options.accepts("player/run_old.png"));
+        assert!(!options.accepts("enemy/run_01.png"));
+    }
+
+    #[test]
+    fn collect_each_file_once() {
+        let dir = std::env::temp_dir().join(format!("sheep-once-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("banner.png"), b"").unwrap();
+        fs::write(dir.join("coin.png"), b"").unwrap();
+
+        let path = dir.to_string_lossy().into_owned();
+        let name = dir.file_name().unwrap().to_string_lossy().into_owned();
+        let args = vec![
+            format!("{}/coin.png", path),
+            format!("{}/../{}/./coin.png", path, name),
+            path.clone(),
+        ];
+
+        let names = collect_inputs(&args, &InputOptions::default())
+            .into_iter()
+            .map(|input| input.name)
+            .collect::<Vec<String>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(names, ["coin", "banner"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_symlink_cycles() {
+        let dir = std::env::temp_dir().join(format!("sheep-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested/idle.png"), b"").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("nested/parent")).unwrap();
+
+        let options = InputOptions::default().recursive(true);
+        let inputs = collect_inputs(&[dir.to_string_lossy().into_owned()], &options);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names = inputs
+            .into_iter()
+            .map(|input| input.name)
+            .collect::<Vec<String>>();
+        assert_eq!(names, ["nested/idle"]);
+    }
+}
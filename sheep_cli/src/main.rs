@@ -1,23 +1,61 @@
 extern crate clap;
+extern crate glob;
 extern crate image;
+extern crate notify;
+#[cfg(feature = "rayon")]
+extern crate rayon;
 extern crate ron;
 extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate sheep;
+extern crate toml;
+extern crate twox_hash;
 
-use clap::{App, AppSettings, Arg, SubCommand};
+mod cache;
+mod config;
+mod input;
+mod stats;
+mod watch;
+
+use cache::{CacheManifest, CachedPage, CachedTrim};
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, SubCommand};
+use config::{AtlasConfig, Oversized, PackerOptions, Project, Strategy};
+use input::{collect_inputs, unique_names, InputFile, NameCollisions};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use serde::Serialize;
 use sheep::{
-    AmethystFormat, AmethystNamedFormat, InputSprite, MaxrectsOptions, MaxrectsPacker,
-    SimplePacker, SpriteSheet,
+    AmethystFormat, AmethystNamedFormat, Cocos2dFormat, Cocos2dOptions, CssFormat, CssOptions,
+    DebugOptions, Format, GridOptions, IndexFormat, IndexOptions, InputSprite, LibGdxFormat,
+    LibGdxOptions, MaxrectsOptions, MaxrectsPacker, MultiFormat, OversizedPolicy, PackError,
+    RustFormat, RustOptions, SerializedNamedSpriteSheet, SerializedSpriteSheet, SimpleOptions,
+    SimplePacker, SimpleStrategy, SparrowFormat, SparrowOptions, SpriteAnchor, SpriteSheet,
+    StableMaxrectsOptions, StableMaxrectsPacker, TrimInfo, UnityFormat, UvFormat, UvOptions,
+    UvOrigin,
 };
-use std::str::FromStr;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 use std::{fs::File, io::prelude::*};
+use watch::watch;
 
-const DEFAULT_FORMAT: &'static str = "amethyst";
-const DEFAULT_PACKER: &'static str = "maxrects";
+const DEFAULT_FORMAT: &str = "amethyst";
+const DEFAULT_PACKER: &str = "maxrects";
 
-const AVAILABLE_FORMATS: [&'static str; 2] = ["amethyst", "amethyst_named"];
-const AVAILABLE_PACKERS: [&'static str; 2] = ["simple", "maxrects"];
+const AVAILABLE_FORMATS: [&str; 9] = [
+    "amethyst",
+    "amethyst_named",
+    "unity",
+    "libgdx",
+    "cocos2d",
+    "sparrow",
+    "css",
+    "rust",
+    "uv",
+];
+const AVAILABLE_PACKERS: [&str; 2] = ["simple", "maxrects"];
 
 fn main() {
     let app = App::new("sheep")
@@ -25,65 +63,115 @@ fn main() {
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(pack_args(
+            SubCommand::with_name("pack").about("Packs supplied images into a spritesheet"),
+        ))
+        .subcommand(pack_args(
+            SubCommand::with_name("watch")
+                .about("Packs supplied images, and packs them again whenever they change")
+                .arg(
+                    Arg::with_name("delay")
+                        .help("Milliseconds to wait for further changes before repacking")
+                        .long("delay")
+                        .takes_value(true)
+                        .default_value("200"),
+                ),
+        ))
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Packs all atlases listed in a project file")
+                .arg(
+                    Arg::with_name("PROJECT")
+                        .help("Project file, defaults to sheep.toml or sheep.ron"),
+                ),
+        )
         .subcommand(
-            SubCommand::with_name("pack")
-                .about("Packs supplied images into a spritesheet")
-                .arg(Arg::with_name("INPUT").required(true).multiple(true))
+            SubCommand::with_name("unpack")
+                .about("Cuts the sprites out of a spritesheet and saves them as separate images")
+                .arg(
+                    Arg::with_name("IMAGE")
+                        .help("Spritesheet image")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("META")
+                        .help("Metadata in the amethyst or amethyst_named format")
+                        .required(true),
+                )
                 .arg(
                     Arg::with_name("output")
-                        .help("Output filename without file extension")
+                        .help("Output directory")
                         .short("o")
                         .long("out")
                         .takes_value(true)
                         .required(false)
-                        .default_value("out"),
+                        .default_value("."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("slice")
+                .about("Cuts a spritesheet into its sprites and saves them as separate images")
+                .after_help(
+                    "The sprites are named after the sheet and their cell, e.g. \
+                     legacy_000.png. To repack them, pass the output directory to \
+                     pack:\n\n    \
+                     sheep slice --cell-size 32x32 --out sprites legacy.png\n    \
+                     sheep pack --format amethyst_named --out legacy_packed sprites",
                 )
                 .arg(
-                    Arg::with_name("packer")
-                        .help("Packing algorithm to use")
-                        .possible_values(&AVAILABLE_PACKERS)
-                        .short("p")
-                        .long("packer")
-                        .takes_value(true)
-                        .required(false)
-                        .default_value(DEFAULT_PACKER),
+                    Arg::with_name("IMAGE")
+                        .help("Spritesheet image")
+                        .required(true),
                 )
                 .arg(
-                    Arg::with_name("format")
-                        .help("Determines the fields present in the serialized output")
-                        .possible_values(&AVAILABLE_FORMATS)
-                        .short("f")
-                        .long("format")
-                        .takes_value(true)
-                        .required(false)
-                        .default_value(DEFAULT_FORMAT),
+                    Arg::with_name("cell_size")
+                        .help("Size of a single cell, e.g. 32x32")
+                        .long("cell-size")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("grid")
+                        .help("Number of columns and rows, e.g. 8x4")
+                        .long("grid")
+                        .takes_value(true),
                 )
                 .arg(
-                    Arg::with_name("options")
-                        .help("Settings that will be passed to the selected packer")
-                        .short("s")
-                        .long("options")
+                    Arg::with_name("detect")
+                        .help(
+                            "Detect loose sprites instead of using a grid, merging sprites \
+                             that are at most this many pixels apart",
+                        )
+                        .long("detect")
                         .takes_value(true)
-                        .multiple(true)
-                        .required(false),
+                        .value_name("DISTANCE"),
+                )
+                .group(
+                    ArgGroup::with_name("layout")
+                        .args(&["cell_size", "grid", "detect"])
+                        .required(true),
                 )
                 .arg(
-                    Arg::with_name("pretty")
-                        .help("The resulting .ron-file is formatted")
-                        .long("pretty")
-                        .required(false),
+                    Arg::with_name("margin")
+                        .help("Space around the whole grid in pixels")
+                        .long("margin")
+                        .takes_value(true)
+                        .default_value("0"),
                 )
                 .arg(
-                    Arg::with_name("trim")
-                        .help("Trim transparent sprite sides")
-                        .short("t")
-                        .long("trim"),
+                    Arg::with_name("spacing")
+                        .help("Space between cells in pixels")
+                        .long("spacing")
+                        .takes_value(true)
+                        .default_value("0"),
                 )
                 .arg(
-                    Arg::with_name("compress")
-                        .help("Use png compression")
-                        .short("c")
-                        .long("compress"),
+                    Arg::with_name("output")
+                        .help("Output directory")
+                        .short("o")
+                        .long("out")
+                        .takes_value(true)
+                        .required(false)
+                        .default_value("."),
                 ),
         );
 
@@ -91,104 +179,746 @@ fn main() {
 
     match matches.subcommand() {
         ("pack", Some(matches)) => {
-            let input = matches
-                .values_of("INPUT")
-                .map(|values| values.map(|it| String::from(it)).collect::<Vec<String>>())
-                .unwrap_or(Vec::new());
+            pack_atlas(&atlas_config(matches));
+        }
+        ("watch", Some(matches)) => {
+            let delay = matches
+                .value_of("delay")
+                .and_then(|value| value.parse::<u64>().ok())
+                .expect("Invalid delay");
+
+            watch(&atlas_config(matches), Duration::from_millis(delay));
+        }
+        ("build", Some(matches)) => {
+            let path = match matches.value_of("PROJECT") {
+                Some(path) => PathBuf::from(path),
+                None => ["sheep.toml", "sheep.ron"]
+                    .iter()
+                    .map(PathBuf::from)
+                    .find(|path| path.is_file())
+                    .expect("No sheep.toml or sheep.ron found in the current directory"),
+            };
 
+            let project = Project::load(&path).unwrap_or_else(|err| panic!("{}", err));
+            for atlas in &project.atlas {
+                pack_atlas(atlas);
+            }
+        }
+        ("unpack", Some(matches)) => {
+            let image = matches
+                .value_of("IMAGE")
+                .expect("Unreachable: param is required");
+            let meta = matches
+                .value_of("META")
+                .expect("Unreachable: param is required");
             let out = matches
                 .value_of("output")
                 .expect("Unreachable: param has default value");
 
-            let mut sprites = load_images(&input);
+            let sheet = load_images(&[String::from(image)])
+                .pop()
+                .expect("Failed to load spritesheet");
 
-            if matches.is_present("trim") {
-                // stride is 4 and alpha index is 3 because rgba8 is used by default
-                sprites = sheep::trim(sprites.as_slice(), 4, 3);
-            }
+            let mut meta_str = String::new();
+            File::open(meta)
+                .and_then(|mut file| file.read_to_string(&mut meta_str))
+                .expect("Failed to read meta file");
 
-            // NOTE(happenslol): By default, we're using rgba8 right now,
-            // so the stride is always 4
-            let results = match matches.value_of("packer") {
-                Some("maxrects") => {
-                    let max_width = matches
-                        .values_of("options")
-                        .and_then(|mut options| options.find(|o| o.starts_with("max_width")))
-                        .and_then(|found| found.split("=").nth(1))
-                        .and_then(|value| value.parse::<u32>().ok())
-                        .unwrap_or(4096);
+            // Names are only known for the named format, otherwise sprites
+            // are named after their position in the metadata
+            let named = ron::de::from_str::<SerializedNamedSpriteSheet>(&meta_str);
+            let (anchors, names) = match named {
+                Ok(named) => {
+                    let names = named
+                        .sprites
+                        .iter()
+                        .map(|it| it.name.clone())
+                        .collect::<Vec<String>>();
+                    (named.anchors(), names)
+                }
+                Err(_) => {
+                    let unnamed = ron::de::from_str::<SerializedSpriteSheet>(&meta_str)
+                        .expect("Failed to parse meta file");
+                    let names = (0..unnamed.sprites.len())
+                        .map(|i| i.to_string())
+                        .collect::<Vec<String>>();
+                    (unnamed.anchors(), names)
+                }
+            };
+
+            // Images are always loaded as rgba8, so the stride is always 4
+            let sprites = sheep::unpack(&sheet, 4, &anchors).unwrap_or_else(|err| {
+                panic!("{} doesn't match the metadata in {}: {}", image, meta, err)
+            });
+
+            write_sprites(out, &sprites, &names);
+        }
+        ("slice", Some(matches)) => {
+            let image = matches
+                .value_of("IMAGE")
+                .expect("Unreachable: param is required");
+            let out = matches
+                .value_of("output")
+                .expect("Unreachable: param has default value");
+
+            let sheet = load_images(&[String::from(image)])
+                .pop()
+                .expect("Failed to load spritesheet");
+
+            // stride is 4 and alpha index is 3 because rgba8 is used by default
+            let (anchors, sprites): (Vec<_>, Vec<_>) =
+                if let Some(distance) = matches.value_of("detect") {
+                    let distance = distance.parse::<u32>().expect("Invalid detect distance");
+                    sheep::detect_sprites(&sheet, 4, 3, distance)
+                        .into_iter()
+                        .unzip()
+                } else {
+                    let options = if let Some(cell_size) = matches.value_of("cell_size") {
+                        let (width, height) = parse_size(cell_size);
+                        GridOptions::cell_size(width, height)
+                    } else {
+                        let grid = matches
+                            .value_of("grid")
+                            .expect("Unreachable: one of the layouts is required");
+                        let (columns, rows) = parse_size(grid);
+                        GridOptions::cell_count(columns, rows)
+                    };
 
-                    let max_height = matches
-                        .values_of("options")
-                        .and_then(|mut options| options.find(|o| o.starts_with("max_height")))
-                        .and_then(|found| found.split("=").nth(1))
+                    let margin = matches
+                        .value_of("margin")
                         .and_then(|value| value.parse::<u32>().ok())
-                        .unwrap_or(4096);
+                        .expect("Invalid margin");
+                    let spacing = matches
+                        .value_of("spacing")
+                        .and_then(|value| value.parse::<u32>().ok())
+                        .expect("Invalid spacing");
 
-                    let options = MaxrectsOptions::default()
-                        .max_width(max_width)
-                        .max_height(max_height);
+                    let options = options.margin(margin).spacing(spacing);
+                    sheep::slice_grid(&sheet, 4, 3, options)
+                        .unwrap_or_else(|err| panic!("Failed to slice {}: {}", image, err))
+                        .into_iter()
+                        .unzip()
+                };
 
-                    sheep::pack::<MaxrectsPacker>(sprites, 4, options)
-                }
-                Some("simple") => sheep::pack::<SimplePacker>(sprites, 4, ()),
-                _ => panic!("Unknown packer"),
-            };
+            let stem = Path::new(image)
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .expect("Failed to extract file name");
+            let names = anchors
+                .iter()
+                .map(|anchor| format!("{}_{:03}", stem, anchor.id))
+                .collect::<Vec<String>>();
 
-            if results.is_empty() {
-                panic!("No output was produced");
-            }
+            write_sprites(out, &sprites, &names);
+        }
+        _ => {}
+    }
+}
 
-            let is_single_sheet = results.len() == 1;
+// Arguments shared by `pack` and `watch`
+fn pack_args<'a, 'b>(command: App<'a, 'b>) -> App<'a, 'b> {
+    command
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Images, directories, glob patterns or @files listing further inputs")
+                .required(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .help("Also include images in subdirectories of input directories")
+                .short("r")
+                .long("recursive"),
+        )
+        .arg(
+            Arg::with_name("include")
+                .help("Only pack images whose path relative to their input matches")
+                .long("include")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .help("Skip images whose path relative to their input matches")
+                .long("exclude")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("names")
+                .help(
+                    "What to do when images share a name: fail, append a number \
+                             or use the full path of the colliding images",
+                )
+                .long("names")
+                .possible_values(&["error", "suffix", "qualify"])
+                .takes_value(true)
+                .default_value("error"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("Output filename without file extension")
+                .short("o")
+                .long("out")
+                .takes_value(true)
+                .required(false)
+                .default_value("out"),
+        )
+        .arg(
+            Arg::with_name("packer")
+                .help("Packing algorithm to use")
+                .possible_values(&AVAILABLE_PACKERS)
+                .short("p")
+                .long("packer")
+                .takes_value(true)
+                .required(false)
+                .default_value(DEFAULT_PACKER),
+        )
+        .arg(
+            Arg::with_name("format")
+                .help("Determines the fields present in the serialized output")
+                .possible_values(&AVAILABLE_FORMATS)
+                .short("f")
+                .long("format")
+                .takes_value(true)
+                .required(false)
+                .default_value(DEFAULT_FORMAT),
+        )
+        .arg(
+            Arg::with_name("options")
+                .help(
+                    "Settings for the selected packer as key=value, e.g. max_width=1024, \
+                     or strategy=rows for the simple packer",
+                )
+                .short("s")
+                .long("options")
+                .takes_value(true)
+                .multiple(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("padding")
+                .help("Transparent pixels to leave between sprites")
+                .long("padding")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("pretty")
+                .help("The resulting .ron-file is formatted")
+                .long("pretty")
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("html")
+                .help("Also write an html page previewing the sprites (css format only)")
+                .long("html"),
+        )
+        .arg(
+            Arg::with_name("uv_inset")
+                .help("Texels to move every sprite edge inwards by (uv format only)")
+                .long("uv-inset")
+                .takes_value(true)
+                .default_value("0"),
+        )
+        .arg(
+            Arg::with_name("uv_origin")
+                .help("Origin of the texture coordinates (uv format only)")
+                .long("uv-origin")
+                .possible_values(&["top_left", "bottom_left"])
+                .takes_value(true)
+                .default_value("top_left"),
+        )
+        .arg(
+            Arg::with_name("trim")
+                .help("Trim transparent sprite sides")
+                .short("t")
+                .long("trim"),
+        )
+        .arg(
+            Arg::with_name("compress")
+                .help("Use png compression")
+                .short("c")
+                .long("compress"),
+        )
+        .arg(
+            Arg::with_name("stable")
+                .help(
+                    "Keep sprites where they are in this amethyst_named metadata, \
+                     usually the output of the previous run",
+                )
+                .long("stable")
+                .takes_value(true)
+                .value_name("PREVIOUS"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .help("Print the occupancy of every sheet, e.g. --stats=json")
+                .long("stats")
+                .possible_values(&["human", "json"])
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .value_name("FORMAT"),
+        )
+        .arg(
+            Arg::with_name("debug")
+                .help(
+                    "Also write <out>.debug.png, showing the outline and name of every sprite \
+                     and the remaining free space",
+                )
+                .long("debug"),
+        )
+        .arg(
+            Arg::with_name("no_cache")
+                .help("Always repack, instead of reusing unchanged images from the last run")
+                .long("no-cache"),
+        )
+}
 
-            for (i, sheet) in results.iter().enumerate() {
-                let filename = if i == 0 && is_single_sheet {
-                    String::from(out)
-                } else {
-                    format!("{}-{:02}", out, i)
+fn atlas_config(matches: &ArgMatches) -> AtlasConfig {
+    let values = |name| {
+        matches
+            .values_of(name)
+            .map(|values| values.map(String::from).collect::<Vec<String>>())
+            .unwrap_or_default()
+    };
+    let value = |name| {
+        matches
+            .value_of(name)
+            .map(String::from)
+            .expect("Unreachable: param has default value")
+    };
+
+    let options = matches.values_of("options").unwrap_or_default();
+    let options = PackerOptions::parse(&options.collect::<Vec<&str>>())
+        .unwrap_or_else(|err| panic!("Invalid packer options: {}", err));
+
+    let config = AtlasConfig {
+        inputs: values("INPUT"),
+        recursive: matches.is_present("recursive"),
+        include: values("include"),
+        exclude: values("exclude"),
+        names: match matches.value_of("names") {
+            Some("suffix") => NameCollisions::Suffix,
+            Some("qualify") => NameCollisions::Qualify,
+            _ => NameCollisions::Error,
+        },
+        packer: value("packer"),
+        options,
+        trim: matches.is_present("trim"),
+        padding: value("padding").parse::<u32>().expect("Invalid padding"),
+        formats: vec![value("format")],
+        output: value("output"),
+        pretty: matches.is_present("pretty"),
+        compress: matches.is_present("compress"),
+        html: matches.is_present("html"),
+        uv_inset: value("uv_inset").parse::<f32>().expect("Invalid uv inset"),
+        uv_origin: value("uv_origin"),
+        cache: !matches.is_present("no_cache"),
+        stable: matches.value_of("stable").map(String::from),
+        stats: if matches.is_present("stats") {
+            Some(String::from(matches.value_of("stats").unwrap_or("human")))
+        } else {
+            None
+        },
+        debug: matches.is_present("debug"),
+    };
+
+    if let Err(err) = config.validate() {
+        panic!("{}", err);
+    }
+
+    config
+}
+
+fn pack_atlas(config: &AtlasConfig) {
+    let (input, names) = atlas_inputs(config);
+    let key = cache::cache_key(config, cache::input_key(config, &input, &names));
+    pack_inputs(config, &input, names, key);
+}
+
+// The images to pack and their names. Name collisions are reported before
+// anything is written, so a failed run doesn't leave sheets behind that
+// don't match their metadata.
+fn atlas_inputs(config: &AtlasConfig) -> (Vec<InputFile>, Vec<String>) {
+    let input = collect_inputs(&config.inputs, &config.input_options());
+    if input.is_empty() {
+        panic!("No input images found for {}", config.output);
+    }
+
+    let names = sprite_names(config, &input);
+    (input, names)
+}
+
+// Packs the inputs and writes the sheets and metadata, unless the cache
+// under `key` is still up to date. See `cache::cache_key`.
+fn pack_inputs(config: &AtlasConfig, input: &[InputFile], names: Vec<String>, key: u64) {
+    let out = config.output.as_str();
+    if let Some(parent) = Path::new(out).parent() {
+        std::fs::create_dir_all(parent).expect("Failed to create output directory");
+    }
+
+    let cached = if config.cache {
+        cache::load_cached(out, key)
+    } else {
+        None
+    };
+
+    let (pages, trims) = match cached {
+        Some(manifest) => {
+            let trims = manifest.trims.into_iter().map(TrimInfo::from).collect();
+            (manifest.pages, trims)
+        }
+        None => {
+            let (pages, trims) = pack_pages(config, input, &names);
+            if config.cache {
+                let manifest = CacheManifest {
+                    key,
+                    pages: pages.clone(),
+                    trims: trims.iter().map(|trim| CachedTrim::from(*trim)).collect(),
                 };
+                cache::write_manifest(out, &manifest);
+            }
+            (pages, trims)
+        }
+    };
 
-                let compress = matches.is_present("compress");
-                write_image(&filename, sheet, compress);
+    let sheets = pages
+        .iter()
+        .map(|page| (page.dimensions, page.anchors()))
+        .collect::<Vec<_>>();
+    let is_single_sheet = sheets.len() == 1;
+    let filenames = page_filenames(out, sheets.len());
 
-                let pretty = matches.is_present("pretty");
+    if let Some(format) = &config.stats {
+        stats::print_stats(format, &filenames, &pages);
+    }
 
-                match matches.value_of("format") {
-                    Some("amethyst_named") => {
-                        let names = get_filenames(&input);
-                        let meta = sheep::encode::<AmethystNamedFormat>(&sheet, names);
-                        write_meta(&filename, meta, pretty);
-                    }
-                    Some("amethyst") => {
-                        let meta = sheep::encode::<AmethystFormat>(&sheet, ());
-                        write_meta(&filename, meta, pretty);
+    if config.debug {
+        for ((_, anchors), filename) in sheets.iter().zip(&filenames) {
+            write_debug_image(filename, anchors, config.padding, &names);
+        }
+    }
+
+    for ((dimensions, anchors), filename) in sheets.iter().zip(&filenames) {
+        let dimensions = *dimensions;
+        let pretty = config.pretty;
+
+        for format in &config.formats {
+            match format.as_str() {
+                "amethyst_named" => {
+                    let meta = AmethystNamedFormat::encode(dimensions, anchors, names.clone());
+                    write_meta(filename, meta, pretty);
+                }
+                "amethyst" => {
+                    let meta = AmethystFormat::encode(dimensions, anchors, ());
+                    write_meta(filename, meta, pretty);
+                }
+                "unity" => {
+                    let names = names.clone();
+                    let meta = UnityFormat::encode(dimensions, anchors, names);
+                    write_text(&format!("{}.png.meta", filename), &meta);
+                }
+                "cocos2d" => {
+                    let texture = format!("{}.png", file_name(filename));
+                    let names = names.clone();
+                    let options = Cocos2dOptions::new(texture, names).trims(trims.clone());
+                    let meta = Cocos2dFormat::encode(dimensions, anchors, options);
+                    write_text(&format!("{}.plist", filename), &meta);
+                }
+                "sparrow" => {
+                    let texture = format!("{}.png", file_name(filename));
+                    let names = names.clone();
+                    let options = SparrowOptions::new(texture, names).trims(trims.clone());
+                    let meta = SparrowFormat::encode(dimensions, anchors, options);
+                    write_text(&format!("{}.xml", filename), &meta);
+                }
+                "css" => {
+                    let image = format!("{}.png", file_name(filename));
+                    let names = names.clone();
+                    let options = CssOptions::new(image, names).html(config.html);
+                    let meta = CssFormat::encode(dimensions, anchors, options);
+                    write_text(&format!("{}.css", filename), &meta.css);
+                    if let Some(html) = meta.html {
+                        write_text(&format!("{}.html", filename), &html);
                     }
-                    _ => panic!("Unknown format"),
-                };
+                }
+                "rust" => {
+                    let names = names.clone();
+                    let meta = RustFormat::encode(dimensions, anchors, RustOptions::new(names));
+                    write_text(&format!("{}.rs", filename), &meta);
+                }
+                "uv" => {
+                    let origin = match config.uv_origin.as_str() {
+                        "bottom_left" => UvOrigin::BottomLeft,
+                        _ => UvOrigin::TopLeft,
+                    };
+                    let options = UvOptions::default()
+                        .names(names.clone())
+                        .inset(config.uv_inset)
+                        .origin(origin);
+                    let meta = UvFormat::encode(dimensions, anchors, options);
+                    write_meta(filename, meta, pretty);
+                }
+                // All pages are written into a single file below
+                "libgdx" => {}
+                _ => panic!("Unknown format"),
+            };
+        }
+    }
+
+    let images = filenames
+        .iter()
+        .map(|filename| format!("{}.png", file_name(filename)))
+        .collect::<Vec<String>>();
+    let sheets = sheets
+        .iter()
+        .map(|(dimensions, anchors)| (*dimensions, anchors.as_slice()))
+        .collect::<Vec<_>>();
+
+    if config.formats.iter().any(|format| format == "libgdx") {
+        let options = LibGdxOptions::new(images.clone(), names.clone()).trims(trims.clone());
+        let atlas = LibGdxFormat::encode(&sheets, options);
+        write_text(&format!("{}.atlas", out), &atlas);
+    }
+
+    // Make it possible to find out which page a sprite ended up on
+    if !is_single_sheet {
+        let options = IndexOptions::new(images).names(names);
+        let index = IndexFormat::encode(&sheets, options);
+        write_meta(&format!("{}-index", out), index, config.pretty);
+    }
+}
+
+// Loads, packs and writes the sheet images, which is the expensive part
+// that the cache allows to skip. Also returns how the sprites were trimmed,
+// which is empty if they weren't.
+fn pack_pages(
+    config: &AtlasConfig,
+    input: &[InputFile],
+    names: &[String],
+) -> (Vec<CachedPage>, Vec<TrimInfo>) {
+    let paths = input.iter().map(|it| &it.path).collect::<Vec<_>>();
+    let sprites = load_images(&paths);
+
+    // The untrimmed sprites are kept to find out how much trimming saved.
+    // stride is 4 and alpha index is 3 because rgba8 is used by default
+    let (sprites, untrimmed, trims) = if config.trim {
+        let trims = sheep::trim_info(sprites.as_slice(), 4, 3);
+        (sheep::trim(sprites.as_slice(), 4, 3), Some(sprites), trims)
+    } else {
+        (sprites, None, Vec::new())
+    };
+
+    // NOTE(happenslol): By default, we're using rgba8 right now,
+    // so the stride is always 4
+    let results = match config.packer.as_str() {
+        "maxrects" => {
+            let mut options = MaxrectsOptions::default().padding(config.padding);
+            if let Some(max_width) = config.options.max_width {
+                options = options.max_width(max_width);
+            }
+            if let Some(max_height) = config.options.max_height {
+                options = options.max_height(max_height);
+            }
+            if let Some(max_pages) = config.options.max_pages {
+                options = options.max_pages(max_pages);
+            }
+            if let Some(oversized) = config.options.oversized {
+                options = options.oversized(match oversized {
+                    Oversized::Separate => OversizedPolicy::Separate,
+                    Oversized::Downscale => OversizedPolicy::Downscale,
+                    Oversized::Error => OversizedPolicy::Error,
+                });
+            }
+
+            match previous_sheet(config) {
+                Some(previous) => {
+                    let previous = previous.anchors_by_name(names);
+                    let options = StableMaxrectsOptions::new(options, previous);
+                    sheep::try_pack::<StableMaxrectsPacker>(sprites, 4, options)
+                }
+                None => sheep::try_pack::<MaxrectsPacker>(sprites, 4, options),
             }
         }
-        _ => {}
+        "simple" => {
+            let mut options = SimpleOptions::default().padding(config.padding);
+            if let Some(max_width) = config.options.max_width {
+                options = options.max_width(max_width);
+            }
+            if let Some(max_height) = config.options.max_height {
+                options = options.max_height(max_height);
+            }
+            if let Some(strategy) = config.options.strategy {
+                options = options.strategy(match strategy {
+                    Strategy::Rows => SimpleStrategy::Rows,
+                    Strategy::Shelves => SimpleStrategy::Shelves,
+                });
+            }
+
+            sheep::try_pack::<SimplePacker>(sprites, 4, options)
+        }
+        _ => panic!("Unknown packer"),
+    };
+    let mut results = results.unwrap_or_else(|err| match err {
+        // Sprite ids are positions in the input, which means nothing to
+        // the user, so the image is named instead
+        PackError::Oversized { id, dimensions } => panic!(
+            "Failed to pack {}: {} ({}) is {}x{}, which is larger than the maximum sheet size",
+            config.output,
+            names[id],
+            input[id].path.display(),
+            dimensions.0,
+            dimensions.1
+        ),
+        err => panic!("Failed to pack {}: {}", config.output, err),
+    });
+
+    if results.is_empty() {
+        panic!("No output was produced");
     }
+
+    let filenames = page_filenames(&config.output, results.len());
+    let pages = results
+        .iter_mut()
+        .zip(&filenames)
+        .map(|(sheet, filename)| {
+            write_image(filename, sheet, config.compress);
+            if let Some(untrimmed) = &untrimmed {
+                sheet.record_trimming(untrimmed);
+            }
+
+            let image_hash = cache::hash_file(Path::new(&format!("{}.png", filename)))
+                .expect("Failed to read written image");
+            CachedPage::new(image_hash, sheet)
+        })
+        .collect();
+
+    (pages, trims)
 }
 
-fn get_filenames(input: &[String]) -> Vec<String> {
-    input
-        .iter()
-        .map(|path| {
-            std::path::PathBuf::from(&path)
-                .file_stem()
-                .and_then(|name| name.to_str())
-                .map(|name| String::from_str(name).expect("could not parse string from file name"))
-                .expect("Failed to extract file name")
+fn sprite_names(config: &AtlasConfig, input: &[InputFile]) -> Vec<String> {
+    unique_names(input, config.names).unwrap_or_else(|err| {
+        panic!(
+            "{}\nPass --names suffix or --names qualify to resolve this",
+            err
+        )
+    })
+}
+
+// The metadata of the previous run for stable packing. It doesn't exist
+// yet on the first run, in which case everything is packed from scratch.
+fn previous_sheet(config: &AtlasConfig) -> Option<SerializedNamedSpriteSheet> {
+    let path = config.stable.as_ref()?;
+    let contents = std::fs::read_to_string(path).ok()?;
+
+    let previous = ron::de::from_str(&contents)
+        .unwrap_or_else(|err| panic!("Invalid amethyst_named metadata in {}: {}", path, err));
+    Some(previous)
+}
+
+// Output paths of the pages without extension, which are only numbered if
+// there's more than one
+fn page_filenames(out: &str, count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| {
+            if count == 1 {
+                String::from(out)
+            } else {
+                format!("{}-{:02}", out, i)
+            }
         })
         .collect()
 }
 
-fn load_images(input: &[String]) -> Vec<InputSprite> {
+fn parse_size(value: &str) -> (u32, u32) {
+    let mut parts = value.split('x').map(|part| part.trim().parse::<u32>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(width)), Some(Ok(height)), None) => (width, height),
+        _ => panic!("Invalid size \"{}\", expected e.g. 32x32", value),
+    }
+}
+
+fn write_sprites(out: &str, sprites: &[InputSprite], names: &[String]) {
+    let mut written = HashSet::new();
+    for (sprite, name) in sprites.iter().zip(names) {
+        // Sprites that were trimmed away completely have no pixels
+        if sprite.dimensions.0 == 0 || sprite.dimensions.1 == 0 {
+            continue;
+        }
+
+        let path = unpacked_path(out, name, &mut written);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Failed to create output directory");
+        }
+
+        image::save_buffer(
+            &path,
+            &sprite.bytes,
+            sprite.dimensions.0,
+            sprite.dimensions.1,
+            image::RGBA(8),
+        )
+        .expect("Failed to write image");
+    }
+}
+
+// Sprite names may contain directories, but they must not be able to
+// escape the output directory. Names that end up at a path that was
+// already written, e.g. `idle` and `./idle`, are numbered like
+// `--names suffix` does.
+fn unpacked_path(out: &str, name: &str, written: &mut HashSet<PathBuf>) -> PathBuf {
+    let relative = Path::new(name)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect::<PathBuf>();
+    if relative.as_os_str().is_empty() {
+        panic!(
+            "Can't unpack sprite {:?}, its name isn't a valid file name",
+            name
+        );
+    }
+
+    // The extension is appended instead of set, so dots in names are kept
+    let base = Path::new(out).join(relative).into_os_string();
+    let mut path = base.clone();
+    path.push(".png");
+
+    let mut suffix = 2;
+    while written.contains(Path::new(&path)) {
+        path = base.clone();
+        path.push(format!("_{}.png", suffix));
+        suffix += 1;
+    }
+
+    let path = PathBuf::from(path);
+    written.insert(path.clone());
+    path
+}
+
+// Metadata refers to sheet images relative to its own location
+fn file_name(output_path: &str) -> String {
+    std::path::PathBuf::from(output_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(String::from)
+        .expect("Failed to extract file name")
+}
+
+fn load_images<P: AsRef<Path> + Sync>(input: &[P]) -> Vec<InputSprite> {
+    #[cfg(feature = "rayon")]
+    let input = input.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let input = input.iter();
     input
-        .iter()
         .map(|path| {
-            let img = image::open(&path).expect("Failed to open image");
+            let img = image::open(path).expect("Failed to open image");
             let img_owned;
             let img = {
                 if let Some(img) = img.as_rgba8() {
@@ -202,7 +932,7 @@ fn load_images(input: &[String]) -> Vec<InputSprite> {
             let dimensions = img.dimensions();
             let bytes = img
                 .pixels()
-                .flat_map(|it| it.data.iter().map(|it| *it))
+                .flat_map(|it| it.data.iter().cloned())
                 .collect::<Vec<u8>>();
 
             InputSprite { dimensions, bytes }
@@ -210,6 +940,27 @@ fn load_images(input: &[String]) -> Vec<InputSprite> {
         .collect()
 }
 
+// Draws the sprite outlines onto a copy of the written sheet, scaled up so
+// the outlines and names don't hide small sprites completely
+fn write_debug_image(output_path: &str, anchors: &[SpriteAnchor], padding: u32, names: &[String]) {
+    let sheet = load_images(&[format!("{}.png", output_path)]).remove(0);
+    let longest = sheet.dimensions.0.max(sheet.dimensions.1).max(1);
+    let options = DebugOptions::default()
+        .scale((2048 / longest).clamp(1, 4))
+        .padding(padding)
+        .names(names.to_vec());
+    let overlay = sheep::debug_overlay(&sheet, 4, 3, anchors, options);
+
+    image::save_buffer(
+        format!("{}.debug.png", output_path),
+        &overlay.bytes,
+        overlay.dimensions.0,
+        overlay.dimensions.1,
+        image::RGBA(8),
+    )
+    .expect("Failed to write debug image");
+}
+
 fn write_image(output_path: &str, sheet: &SpriteSheet, compress: bool) {
     let filename = format!("{}.png", output_path);
 
@@ -258,3 +1009,31 @@ fn write_meta<S: Serialize>(output_path: &str, meta: S, pretty: bool) {
         .write_all(meta_str.as_bytes())
         .expect("Failed to write meta file");
 }
+
+fn write_text(filename: &str, text: &str) {
+    let mut file = File::create(filename).expect("Failed to create meta file");
+    file.write_all(text.as_bytes())
+        .expect("Failed to write meta file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_unpacked_paths() {
+        let mut written = HashSet::new();
+        let mut path = |name| unpacked_path("out", name, &mut written);
+
+        assert_eq!(path("player/run.01"), Path::new("out/player/run.01.png"));
+        assert_eq!(path("../idle"), Path::new("out/idle.png"));
+        assert_eq!(path("./idle"), Path::new("out/idle_2.png"));
+        assert_eq!(path("idle"), Path::new("out/idle_3.png"));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unpack_without_file_name() {
+        unpacked_path("out", ".", &mut HashSet::new());
+    }
+}
@@ -0,0 +1,160 @@
+use cache::CachedPage;
+use sheep::SheetStats;
+
+#[derive(Serialize)]
+struct StatsReport {
+    sheets: Vec<SheetReport>,
+    total: StatsValues,
+}
+
+#[derive(Serialize)]
+struct SheetReport {
+    image: String,
+    width: u32,
+    height: u32,
+    #[serde(flatten)]
+    stats: StatsValues,
+}
+
+#[derive(Serialize)]
+struct StatsValues {
+    sprites: usize,
+    aliases: usize,
+    area: u64,
+    used_area: u64,
+    wasted_area: u64,
+    occupancy: f64,
+    trimmed_bytes: u64,
+}
+
+impl From<SheetStats> for StatsValues {
+    fn from(stats: SheetStats) -> Self {
+        StatsValues {
+            sprites: stats.sprites,
+            aliases: stats.aliases,
+            area: stats.area,
+            used_area: stats.used_area,
+            wasted_area: stats.wasted_area(),
+            occupancy: stats.occupancy(),
+            trimmed_bytes: stats.trimmed_bytes,
+        }
+    }
+}
+
+/// Prints the stats of every page to stdout, `filenames` are the output
+/// paths of the pages without extension.
+pub fn print_stats(format: &str, filenames: &[String], pages: &[CachedPage]) {
+    let report = StatsReport {
+        sheets: filenames
+            .iter()
+            .zip(pages)
+            .map(|(filename, page)| SheetReport {
+                image: format!("{}.png", filename),
+                width: page.dimensions.0,
+                height: page.dimensions.1,
+                stats: page.stats().into(),
+            })
+            .collect(),
+        total: total(pages).into(),
+    };
+
+    match format {
+        "json" => {
+            let json =
+                serde_json::to_string_pretty(&report).expect("Failed to serialize packing stats");
+            println!("{}", json);
+        }
+        _ => print!("{}", human(&report)),
+    }
+}
+
+fn total(pages: &[CachedPage]) -> SheetStats {
+    pages
+        .iter()
+        .map(CachedPage::stats)
+        .fold(SheetStats::default(), |total, stats| SheetStats {
+            sprites: total.sprites + stats.sprites,
+            aliases: total.aliases + stats.aliases,
+            area: total.area + stats.area,
+            used_area: total.used_area + stats.used_area,
+            trimmed_bytes: total.trimmed_bytes + stats.trimmed_bytes,
+        })
+}
+
+fn human(report: &StatsReport) -> String {
+    let mut lines = report
+        .sheets
+        .iter()
+        .map(|sheet| {
+            format!(
+                "{} ({}x{}): {}",
+                sheet.image,
+                sheet.width,
+                sheet.height,
+                human_values(&sheet.stats)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    if report.sheets.len() > 1 {
+        lines.push(format!("total: {}", human_values(&report.total)));
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn human_values(stats: &StatsValues) -> String {
+    format!(
+        "{} sprites, {} aliases, {:.1}% occupied, {} pixels wasted, {} bytes trimmed",
+        stats.sprites,
+        stats.aliases,
+        stats.occupancy * 100.0,
+        stats.wasted_area,
+        stats.trimmed_bytes
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cache::CachedStats;
+
+    fn page(area: u64, used_area: u64) -> CachedPage {
+        CachedPage {
+            image_hash: 0,
+            dimensions: (10, (area / 10) as u32),
+            anchors: Vec::new(),
+            stats: CachedStats {
+                sprites: 2,
+                aliases: 1,
+                area,
+                used_area,
+                trimmed_bytes: 16,
+            },
+        }
+    }
+
+    #[test]
+    fn human_report() {
+        let pages = vec![page(100, 50), page(200, 150)];
+        let report = StatsReport {
+            sheets: vec![SheetReport {
+                image: String::from("out-00.png"),
+                width: 10,
+                height: 10,
+                stats: pages[0].stats().into(),
+            }],
+            total: total(&pages).into(),
+        };
+        assert_eq!(
+            human(&report),
+            "out-00.png (10x10): 2 sprites, 1 aliases, 50.0% occupied, \
+             50 pixels wasted, 16 bytes trimmed\n"
+        );
+
+        let total = total(&pages);
+        assert_eq!(total.sprites, 4);
+        assert_eq!(total.wasted_area(), 100);
+        assert!((total.occupancy() - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,159 @@
+use cache::{cache_key, input_key};
+use config::AtlasConfig;
+use input::watch_paths;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::env;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+use {atlas_inputs, pack_inputs};
+
+/// Packs the atlas, then keeps packing it again whenever its inputs change.
+/// Changes are collected until there haven't been any for `delay`, so
+/// saving a lot of files at once only leads to a single repack.
+///
+/// Errors while packing, like missing or broken images, are reported
+/// without stopping, since they're usually fixed by a later change.
+pub fn watch(config: &AtlasConfig, delay: Duration) {
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, delay).expect("Failed to create file watcher");
+
+    for (path, recursive) in watch_paths(&config.inputs, config.recursive) {
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        watcher
+            .watch(&path, mode)
+            .unwrap_or_else(|err| panic!("Failed to watch {}: {}", path.display(), err));
+    }
+
+    let outputs = Outputs::new(config);
+    let mut last_key = repack(config, None);
+    println!("Watching for changes, press Ctrl-C to stop");
+
+    while let Ok(event) = rx.recv() {
+        // The debounced events of a burst of changes arrive together, so
+        // all of them are taken before packing once
+        let mut changed = is_input_change(event, &outputs);
+        while let Ok(event) = rx.try_recv() {
+            changed |= is_input_change(event, &outputs);
+        }
+
+        if changed {
+            last_key = repack(config, last_key);
+        }
+    }
+}
+
+// Writing the sheets, metadata and cache triggers events as well, and so
+// does the hint file for stable packing, which is usually the metadata of
+// the last run. Those don't need another repack.
+struct Outputs {
+    directory: PathBuf,
+    prefix: String,
+    stable: Option<PathBuf>,
+}
+
+impl Outputs {
+    fn new(config: &AtlasConfig) -> Self {
+        let output = absolute(Path::new(&config.output));
+        let directory = output.parent().map(Path::to_path_buf).unwrap_or_default();
+        let prefix = output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Outputs {
+            directory,
+            prefix,
+            stable: config.stable.as_ref().map(|path| absolute(Path::new(path))),
+        }
+    }
+
+    // All outputs are named `<out>.<ext>` or `<out>-<suffix>.<ext>`
+    fn contains(&self, path: &Path) -> bool {
+        if self.stable.as_deref() == Some(path) {
+            return true;
+        }
+
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => return false,
+        };
+        path.parent() == Some(self.directory.as_path())
+            && name.starts_with(&self.prefix)
+            && name[self.prefix.len()..].starts_with(['.', '-'])
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_input_change(event: DebouncedEvent, outputs: &Outputs) -> bool {
+    match event {
+        // Notices are sent right away, the actual event follows
+        // after the delay
+        DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => false,
+        DebouncedEvent::Error(err, path) => {
+            eprintln!("Error while watching {:?}: {}", path, err);
+            false
+        }
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Chmod(path)
+        | DebouncedEvent::Remove(path) => !outputs.contains(&path),
+        DebouncedEvent::Rename(from, to) => !outputs.contains(&from) || !outputs.contains(&to),
+        DebouncedEvent::Rescan => true,
+    }
+}
+
+// Packs the atlas if the key of its inputs differs from `last_key`, and
+// returns the new key, or `None` if packing failed. The hint file for
+// stable packing isn't part of that key, since packing writes it.
+fn repack(config: &AtlasConfig, last_key: Option<u64>) -> Option<u64> {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        // Names are part of the key, so renaming or moving an input
+        // repacks even if no image changed. The inputs are only hashed
+        // once, their key is passed on for the cache.
+        let (input, names) = atlas_inputs(config);
+        let key = input_key(config, &input, &names);
+        if Some(key) != last_key {
+            pack_inputs(config, &input, names, cache_key(config, key));
+            println!("Packed {}", config.output);
+        }
+
+        key
+    }));
+
+    // The panic message has already been printed at this point
+    result.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignore_outputs() {
+        let config = AtlasConfig {
+            output: String::from("out/sheet"),
+            stable: Some(String::from("hints/sheet.ron")),
+            ..AtlasConfig::default()
+        };
+        let outputs = Outputs::new(&config);
+
+        assert!(outputs.contains(&absolute(Path::new("out/sheet.png"))));
+        assert!(outputs.contains(&absolute(Path::new("out/sheet-01.png"))));
+        assert!(outputs.contains(&absolute(Path::new("out/sheet.sheep-cache"))));
+        assert!(outputs.contains(&absolute(Path::new("hints/sheet.ron"))));
+        assert!(!outputs.contains(&absolute(Path::new("out/sheets.png"))));
+        assert!(!outputs.contains(&absolute(Path::new("sheet.png"))));
+    }
+}